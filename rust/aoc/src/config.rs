@@ -0,0 +1,239 @@
+use common::config::{Config as DayConfig, Input};
+
+pub const USAGE: &str = "\
+Usage: aoc <COMMAND>
+
+Commands:
+    run <DAY|all> [PART] [FILE] [OPTIONS]   Solve one day, or every registered day
+    disasm <DAY|FILE|->                     Print an Intcode program as an assembly listing
+    debug <DAY|FILE>                        Step through an Intcode program interactively
+    profile <DAY|FILE|-> [OPTIONS]          Report where an Intcode program spends its time
+    analyze <DAY|FILE|-> [OPTIONS]          Map an Intcode program's control flow statically
+    fuzz [OPTIONS]                          Check random Intcode programs run the same on every engine
+    help                                    Print this message
+
+Run `aoc run <DAY> --help` to see the options accepted by `run`.
+
+Options for profile:
+    -i, --input <N,N,...>   Values to feed the program
+    --folded <FILE>         Write folded call stacks to FILE, for flamegraph.pl or inferno
+
+Options for analyze:
+    --dot <FILE>            Write the control-flow graph to FILE, for Graphviz
+
+Options for fuzz:
+    --seed <N>              Seed for generating programs (default: from the clock)
+    --runs <N>              Number of programs to run (default: 1000)
+    --day <2|5|7|9>         Only use the opcodes known by that day (default: 9)";
+
+pub enum Selection {
+    All,
+    Day(u8),
+}
+
+impl Selection {
+    pub fn new(raw_value: String) -> Result<Self, String> {
+        if raw_value.to_lowercase() == "all" {
+            return Ok(Selection::All);
+        }
+
+        match raw_value.parse::<u8>() {
+            Ok(day) => Ok(Selection::Day(day)),
+            Err(_) => Err(format!("Invalid day: {}", raw_value)),
+        }
+    }
+}
+
+// Where an Intcode program is read from: a day's default input, a file or stdin
+pub enum Source {
+    Day(u8),
+    File(String),
+    Stdin,
+}
+
+impl Source {
+    pub fn new(raw_value: String) -> Self {
+        if raw_value == "-" {
+            return Source::Stdin;
+        }
+
+        match raw_value.parse::<u8>() {
+            Ok(day) => Source::Day(day),
+            Err(_) => Source::File(raw_value),
+        }
+    }
+}
+
+pub enum Command {
+    Run {
+        selection: Selection,
+        config: DayConfig,
+    },
+    Disasm {
+        source: Source,
+    },
+    Debug {
+        source: Source,
+    },
+    Profile {
+        source: Source,
+        inputs: Vec<i64>,
+        folded: Option<String>,
+    },
+    Analyze {
+        source: Source,
+        dot: Option<String>,
+    },
+    Fuzz {
+        seed: u64,
+        runs: u64,
+        day: u8,
+    },
+    Help,
+}
+
+pub struct Config {
+    pub command: Command,
+}
+
+impl Config {
+    pub fn new(mut args: std::env::Args) -> Result<Config, String> {
+        args.next();
+
+        let command = match args.next() {
+            Some(raw_command) => raw_command,
+            None => return Err(String::from("Didn't get a command")),
+        };
+
+        let command = match command.as_str() {
+            "run" => {
+                let selection = match args.next() {
+                    Some(raw_selection) => Selection::new(raw_selection)?,
+                    None => return Err(String::from("Didn't get a day")),
+                };
+
+                let config = DayConfig::from_args(args)?;
+
+                if let (Selection::All, Input::File(_)) | (Selection::All, Input::Stdin) =
+                    (&selection, &config.input)
+                {
+                    return Err(String::from(
+                        "An input can't be given when running all days",
+                    ));
+                }
+
+                Command::Run { selection, config }
+            }
+            "disasm" => Command::Disasm {
+                source: source(&mut args)?,
+            },
+            "debug" => match source(&mut args)? {
+                // Commands are read from stdin, so the program can't be
+                Source::Stdin => {
+                    return Err(String::from("The debugger can't read a program from stdin"))
+                }
+                source => Command::Debug { source },
+            },
+            "profile" => profile(args)?,
+            "analyze" => analyze(args)?,
+            "fuzz" => fuzz(args)?,
+            "help" | "-h" | "--help" => Command::Help,
+            _ => return Err(format!("Invalid command: {}", command)),
+        };
+
+        Ok(Config { command })
+    }
+}
+
+fn source(args: &mut std::env::Args) -> Result<Source, String> {
+    let source = match args.next() {
+        Some(raw_source) => Source::new(raw_source),
+        None => return Err(String::from("Didn't get a program")),
+    };
+
+    match args.next() {
+        Some(arg) => Err(format!("Unexpected argument: {}", arg)),
+        None => Ok(source),
+    }
+}
+
+fn profile(mut args: std::env::Args) -> Result<Command, String> {
+    let source = match args.next() {
+        Some(raw_source) => Source::new(raw_source),
+        None => return Err(String::from("Didn't get a program")),
+    };
+    let mut inputs = Vec::new();
+    let mut folded = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| match args.next() {
+            Some(v) => Ok(v),
+            None => Err(format!("Didn't get a value for {}", name)),
+        };
+
+        match arg.as_str() {
+            "-i" | "--input" => {
+                let raw_inputs = value(&arg)?;
+                inputs = intcode::parse_program(&raw_inputs)
+                    .map_err(|_| format!("Invalid inputs: {}", raw_inputs))?;
+            }
+            "--folded" => folded = Some(value(&arg)?),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    Ok(Command::Profile {
+        source,
+        inputs,
+        folded,
+    })
+}
+
+fn analyze(mut args: std::env::Args) -> Result<Command, String> {
+    let source = match args.next() {
+        Some(raw_source) => Source::new(raw_source),
+        None => return Err(String::from("Didn't get a program")),
+    };
+    let mut dot = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dot" => match args.next() {
+                Some(path) => dot = Some(path),
+                None => return Err(format!("Didn't get a value for {}", arg)),
+            },
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    Ok(Command::Analyze { source, dot })
+}
+
+fn fuzz(mut args: std::env::Args) -> Result<Command, String> {
+    let mut seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    let mut runs = 1000;
+    let mut day = 9;
+
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => return Err(format!("Didn't get a value for {}", arg)),
+        };
+        let invalid = |_| format!("Invalid value for {}: {}", arg, value);
+
+        match arg.as_str() {
+            "--seed" => seed = value.parse().map_err(invalid)?,
+            "--runs" => runs = value.parse().map_err(invalid)?,
+            "--day" => day = value.parse().map_err(invalid)?,
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    if intcode::day_opcodes(day).is_none() {
+        return Err(format!("No Intcode opcodes for day {}", day));
+    }
+
+    Ok(Command::Fuzz { seed, runs, day })
+}
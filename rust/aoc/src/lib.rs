@@ -0,0 +1,207 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+
+use common::config::Config as DayConfig;
+use common::Day;
+
+use crate::config::{Command, Selection, Source, USAGE};
+
+pub mod config;
+
+// Intcode limits generous for any real puzzle, but that keep a spinning program from hanging
+// the whole run. Options given on the command line win.
+const RUN_ALL_OPTIONS: [(&str, &str); 3] = [
+    ("steps", "1000000000"),
+    ("time", "30"),
+    ("detect-loops", "true"),
+];
+
+pub fn days() -> Vec<Box<dyn Day>> {
+    vec![
+        Box::new(day1::Day1),
+        Box::new(day2::Day2),
+        Box::new(day3::Day3),
+        Box::new(day4::Day4),
+        Box::new(day5::Day5),
+        Box::new(day6::Day6),
+        Box::new(day7::Day7),
+        Box::new(day8::Day8),
+        Box::new(day9::Day9),
+        Box::new(day10::Day10),
+    ]
+}
+
+pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
+    match config.command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::Run {
+            selection: Selection::All,
+            config,
+        } => run_all(&config),
+        Command::Run {
+            selection: Selection::Day(number),
+            config,
+        } => {
+            let days = days();
+            match days.iter().find(|d| d.number() == number) {
+                Some(day) => common::run(day.as_ref(), config),
+                None => Err(format!("Day {} is not registered", number).into()),
+            }
+        }
+        Command::Disasm { source } => {
+            let program = read_program(&source)?;
+            println!("{}", intcode::listing(&program));
+            Ok(())
+        }
+        Command::Debug { source } => {
+            let program = read_program(&source)?;
+            let mut debugger = intcode::Debugger::new(intcode::Intcode::new(&program));
+            debugger.run(io::stdin().lock(), io::stdout())?;
+            Ok(())
+        }
+        Command::Profile {
+            source,
+            inputs,
+            folded,
+        } => profile(&source, inputs, folded),
+        Command::Analyze { source, dot } => analyze(&source, dot),
+        Command::Fuzz { seed, runs, day } => fuzz(seed, runs, day),
+    }
+}
+
+fn read_program(source: &Source) -> Result<Vec<i64>, Box<dyn Error>> {
+    let contents = match source {
+        Source::Day(day) => fs::read_to_string(common::default_input(*day))?,
+        Source::File(filename) => fs::read_to_string(filename)?,
+        Source::Stdin => {
+            let mut contents = String::new();
+            io::stdin().read_to_string(&mut contents)?;
+            contents
+        }
+    };
+
+    Ok(intcode::parse_program(&contents)?)
+}
+
+fn profile(
+    source: &Source,
+    inputs: Vec<i64>,
+    folded: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let program = read_program(source)?;
+    let mut intcode = intcode::Intcode::new(&program);
+    intcode.set_profiling(true);
+    intcode.push_inputs(inputs);
+
+    let state = intcode.run()?;
+    let outputs = intcode.drain_outputs();
+    let profile = intcode.take_profile().unwrap_or_default();
+
+    println!("Finished with {:?}, outputs: {:?}\n", state, outputs);
+    print!("{}", profile.summary());
+
+    if let Some(path) = folded {
+        fs::write(&path, profile.folded() + "\n")?;
+        println!("\nWrote folded stacks to {}", path);
+    }
+
+    Ok(())
+}
+
+fn analyze(source: &Source, dot: Option<String>) -> Result<(), Box<dyn Error>> {
+    let program = read_program(source)?;
+    let analysis = intcode::analyze(&program);
+
+    print!("{}", analysis.summary());
+
+    if let Some(path) = dot {
+        fs::write(&path, analysis.dot())?;
+        println!("\nWrote control-flow graph to {}", path);
+    }
+
+    Ok(())
+}
+
+fn fuzz(seed: u64, runs: u64, day: u8) -> Result<(), Box<dyn Error>> {
+    let opcodes = intcode::day_opcodes(day).unwrap_or_default();
+    let fuzzer = intcode::Fuzzer::new(seed).opcodes(&opcodes).runs(runs);
+
+    match fuzzer.run() {
+        Ok(runs) => {
+            println!("Every engine agreed on {} programs (seed {})", runs, seed);
+            Ok(())
+        }
+        Err(intcode::FuzzError::Mismatch(mismatch)) => {
+            println!("Engines disagree (seed {}), shrunk to:\n{}", seed, mismatch);
+            Err("Found a mismatch between engines".into())
+        }
+        Err(e) => Err(e.to_string().into()),
+    }
+}
+
+fn run_all(config: &DayConfig) -> Result<(), Box<dyn Error>> {
+    let mut failures = 0;
+
+    let mut config = config.clone();
+    for (name, value) in RUN_ALL_OPTIONS.iter() {
+        config
+            .options
+            .entry(name.to_string())
+            .or_insert_with(|| value.to_string());
+    }
+
+    for day in days() {
+        let results = match common::solve(day.as_ref(), &config) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("Day {} failed: {}", day.number(), e);
+                failures += 1;
+                continue;
+            }
+        };
+
+        for result in results {
+            match result {
+                Ok(report) => {
+                    let label = format!(
+                        "Day {} part {}",
+                        report.day,
+                        report.part.number().unwrap_or(0)
+                    );
+                    println!("{}", common::render(&report, &config, Some(label)));
+                }
+                Err(failure) => {
+                    eprintln!(
+                        "Day {} part {} failed: {}",
+                        failure.day,
+                        failure.part.number().unwrap_or(0),
+                        failure.error
+                    );
+                    failures += 1;
+                }
+            }
+        }
+    }
+
+    if failures > 0 {
+        return Err(format!("{} puzzle(s) failed", failures).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_are_registered_in_order() {
+        let numbers = days().iter().map(|d| d.number()).collect::<Vec<u8>>();
+
+        assert_eq!((1..=10).collect::<Vec<u8>>(), numbers);
+    }
+}
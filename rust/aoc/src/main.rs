@@ -0,0 +1,17 @@
+use std::{env, process};
+
+use aoc::config;
+
+fn main() {
+    let config = config::Config::new(env::args()).unwrap_or_else(|err| {
+        eprintln!("Error parsing arguments: {}", err);
+
+        process::exit(1);
+    });
+
+    if let Err(e) = aoc::run(config) {
+        eprintln!("Application error: {}", e);
+
+        process::exit(1);
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Answer {
+    Integer(i64),
+    Text(String),
+    Image(Vec<String>),
+    // A part without a solution yet, which isn't a failure
+    Unsolved,
+}
+
+impl Display for Answer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::Integer(i) => write!(f, "{}", i),
+            Answer::Text(s) => write!(f, "{}", s),
+            Answer::Image(rows) => write!(f, "{}", rows.join("\n")),
+            Answer::Unsolved => write!(f, "not solved yet"),
+        }
+    }
+}
+
+impl From<i64> for Answer {
+    fn from(value: i64) -> Self {
+        Answer::Integer(value)
+    }
+}
+
+impl From<i32> for Answer {
+    fn from(value: i32) -> Self {
+        Answer::Integer(value as i64)
+    }
+}
+
+impl From<isize> for Answer {
+    fn from(value: isize) -> Self {
+        Answer::Integer(value as i64)
+    }
+}
+
+impl From<usize> for Answer {
+    fn from(value: usize) -> Self {
+        Answer::Integer(value as i64)
+    }
+}
+
+impl From<String> for Answer {
+    fn from(value: String) -> Self {
+        Answer::Text(value)
+    }
+}
+
+impl From<&str> for Answer {
+    fn from(value: &str) -> Self {
+        Answer::Text(String::from(value))
+    }
+}
+
+impl From<Vec<String>> for Answer {
+    fn from(rows: Vec<String>) -> Self {
+        Answer::Image(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_integer() {
+        assert_eq!("-42", Answer::from(-42i64).to_string());
+    }
+
+    #[test]
+    fn display_image() {
+        let image = Answer::from(vec![String::from(" *"), String::from("* ")]);
+
+        assert_eq!(" *\n* ", image.to_string());
+    }
+}
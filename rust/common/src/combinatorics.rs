@@ -0,0 +1,406 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// Every ordering of `items`, by Heap's algorithm, so each one differs from the last by a swap
+pub fn permutations<T: Clone>(items: &[T]) -> Permutations<T> {
+    Permutations {
+        items: items.to_vec(),
+        counters: vec![0; items.len()],
+        index: 0,
+        first: true,
+    }
+}
+
+pub struct Permutations<T> {
+    items: Vec<T>,
+    counters: Vec<usize>,
+    index: usize,
+    first: bool,
+}
+
+impl<T: Clone> Iterator for Permutations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.first {
+            self.first = false;
+            return Some(self.items.clone());
+        }
+
+        while self.index < self.items.len() {
+            if self.counters[self.index] < self.index {
+                if self.index.is_multiple_of(2) {
+                    self.items.swap(0, self.index);
+                } else {
+                    self.items.swap(self.counters[self.index], self.index);
+                }
+
+                self.counters[self.index] += 1;
+                self.index = 0;
+                return Some(self.items.clone());
+            }
+
+            self.counters[self.index] = 0;
+            self.index += 1;
+        }
+
+        None
+    }
+}
+
+// Every way to pick `k` of `items`, keeping their order, in lexicographic order of positions
+pub fn combinations<T: Clone>(items: &[T], k: usize) -> Combinations<T> {
+    Combinations {
+        items: items.to_vec(),
+        indices: (0..k).collect(),
+        done: k > items.len(),
+    }
+}
+
+pub struct Combinations<T> {
+    items: Vec<T>,
+    indices: Vec<usize>,
+    done: bool,
+}
+
+impl<T: Clone> Iterator for Combinations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let combination = self
+            .indices
+            .iter()
+            .map(|&i| self.items[i].clone())
+            .collect();
+
+        // Bump the rightmost index that still has room, then reset the ones after it
+        let (n, k) = (self.items.len(), self.indices.len());
+        match (0..k).rev().find(|&i| self.indices[i] < n - k + i) {
+            Some(i) => {
+                self.indices[i] += 1;
+                for j in i + 1..k {
+                    self.indices[j] = self.indices[j - 1] + 1;
+                }
+            }
+            None => self.done = true,
+        }
+
+        Some(combination)
+    }
+}
+
+// One value from each set, for every way of choosing them; the last set varies fastest
+pub fn cartesian_product<T: Clone>(sets: &[Vec<T>]) -> CartesianProduct<T> {
+    CartesianProduct {
+        sets: sets.to_vec(),
+        indices: vec![0; sets.len()],
+        done: sets.iter().any(|set| set.is_empty()),
+    }
+}
+
+pub struct CartesianProduct<T> {
+    sets: Vec<Vec<T>>,
+    indices: Vec<usize>,
+    done: bool,
+}
+
+impl<T: Clone> Iterator for CartesianProduct<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let product = self
+            .indices
+            .iter()
+            .zip(&self.sets)
+            .map(|(&i, set)| set[i].clone())
+            .collect();
+
+        // Counts like an odometer, where each wheel has as many positions as its set
+        self.done = true;
+        for i in (0..self.sets.len()).rev() {
+            self.indices[i] += 1;
+            if self.indices[i] < self.sets[i].len() {
+                self.done = false;
+                break;
+            }
+
+            self.indices[i] = 0;
+        }
+
+        Some(product)
+    }
+}
+
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+// `items.iter().map(f).collect()`, spread over every available core
+pub fn parallel_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    map_on_threads(items, available_threads(), f)
+}
+
+fn map_on_threads<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = threads.min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    // Threads take the next item as they finish one, so uneven work still balances out
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        match items.get(i) {
+                            Some(item) => results.push((i, f(item))),
+                            None => return results,
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Worker thread panicked"))
+            .collect::<Vec<(usize, R)>>()
+    });
+
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// Runs `f` over `items` in parallel and returns what it found, in the order of `items`. Each
+// thread gets its own state from `init` to reuse between items. With `first_only` the search
+// stops as soon as the earliest match is known and at most that one is returned.
+pub fn parallel_search<T, S, R, I, F>(items: &[T], first_only: bool, init: I, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    I: Fn() -> S + Sync,
+    F: Fn(&mut S, &T) -> Option<R> + Sync,
+{
+    search_on_threads(items, available_threads(), first_only, init, f)
+}
+
+fn search_on_threads<T, S, R, I, F>(
+    items: &[T],
+    threads: usize,
+    first_only: bool,
+    init: I,
+    f: F,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    I: Fn() -> S + Sync,
+    F: Fn(&mut S, &T) -> Option<R> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    // Items are handed out in order, so once one matches every earlier item has been taken and
+    // nothing after it needs to be looked at
+    let limit = AtomicUsize::new(usize::MAX);
+    let found = Mutex::new(Vec::new());
+
+    let work = || {
+        let mut state = init();
+        loop {
+            let i = next.fetch_add(1, Ordering::SeqCst);
+            if i >= items.len() || i > limit.load(Ordering::SeqCst) {
+                return;
+            }
+
+            if let Some(result) = f(&mut state, &items[i]) {
+                if first_only {
+                    limit.fetch_min(i, Ordering::SeqCst);
+                }
+
+                found
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push((i, result));
+            }
+        }
+    };
+
+    match threads.min(items.len()) {
+        0 | 1 => work(),
+        threads => thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(work);
+            }
+        }),
+    }
+
+    let mut found = found.into_inner().unwrap_or_else(|e| e.into_inner());
+    found.sort_unstable_by_key(|(i, _)| *i);
+    if first_only {
+        found.truncate(1);
+    }
+
+    found.into_iter().map(|(_, result)| result).collect()
+}
+
+// The item with the largest key and the key itself, evaluating keys in parallel. Ties go to the
+// first item, and if any key fails, the first failure is returned.
+pub fn parallel_try_max_by_key<T, K, E, F>(items: &[T], key: F) -> Result<Option<(&T, K)>, E>
+where
+    T: Sync,
+    K: Ord + Send,
+    E: Send,
+    F: Fn(&T) -> Result<K, E> + Sync,
+{
+    parallel_map(items, key).into_iter().zip(items).try_fold(
+        None,
+        |best: Option<(&T, K)>, (key, item)| {
+            let key = key?;
+            Ok(match best {
+                Some((_, ref best_key)) if *best_key >= key => best,
+                _ => Some((item, key)),
+            })
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn permutations_are_distinct_and_complete() {
+        let all = permutations(&[1, 2, 3, 4]).collect::<Vec<Vec<i32>>>();
+        let distinct = all.iter().collect::<HashSet<&Vec<i32>>>();
+
+        assert_eq!(24, all.len());
+        assert_eq!(24, distinct.len());
+        assert_eq!(vec![1, 2, 3, 4], all[0]);
+        assert_eq!(1, permutations::<i32>(&[]).count());
+    }
+
+    #[test]
+    fn combinations_in_order() {
+        assert_eq!(
+            vec![
+                vec!['a', 'b'],
+                vec!['a', 'c'],
+                vec!['a', 'd'],
+                vec!['b', 'c'],
+                vec!['b', 'd'],
+                vec!['c', 'd']
+            ],
+            combinations(&['a', 'b', 'c', 'd'], 2).collect::<Vec<Vec<char>>>()
+        );
+        assert_eq!(1, combinations(&[1, 2], 0).count());
+        assert_eq!(0, combinations(&[1, 2], 3).count());
+    }
+
+    #[test]
+    fn cartesian_product_like_an_odometer() {
+        assert_eq!(
+            vec![
+                vec![0, 5],
+                vec![0, 6],
+                vec![1, 5],
+                vec![1, 6],
+                vec![2, 5],
+                vec![2, 6]
+            ],
+            cartesian_product(&[vec![0, 1, 2], vec![5, 6]]).collect::<Vec<Vec<i32>>>()
+        );
+        assert_eq!(0, cartesian_product(&[vec![1], vec![]]).count());
+    }
+
+    #[test]
+    fn parallel_map_keeps_order() {
+        let items = (0..1000).collect::<Vec<u64>>();
+
+        assert_eq!(
+            items.iter().map(|i| i * i).collect::<Vec<u64>>(),
+            parallel_map(&items, |i| i * i)
+        );
+        assert_eq!(
+            items.iter().map(|i| i * i).collect::<Vec<u64>>(),
+            map_on_threads(&items, 4, |i| i * i)
+        );
+    }
+
+    #[test]
+    fn search_stops_at_the_first_match() {
+        let items = (0..10000).collect::<Vec<u32>>();
+        let visited = AtomicUsize::new(0);
+        let multiple_of_7 = |_: &mut (), i: &u32| {
+            visited.fetch_add(1, Ordering::SeqCst);
+            Some(*i).filter(|i| *i > 0 && i.is_multiple_of(7))
+        };
+
+        for &threads in &[1, 4] {
+            visited.store(0, Ordering::SeqCst);
+            assert_eq!(
+                vec![7],
+                search_on_threads(&items, threads, true, || (), multiple_of_7)
+            );
+            assert!(visited.load(Ordering::SeqCst) < 100);
+        }
+    }
+
+    #[test]
+    fn search_finds_every_match_in_order() {
+        let items = (0..100).collect::<Vec<u32>>();
+        let found = search_on_threads(
+            &items,
+            4,
+            false,
+            || 0,
+            |calls, i| {
+                *calls += 1;
+                Some(*i).filter(|i| i.is_multiple_of(25))
+            },
+        );
+
+        assert_eq!(vec![0, 25, 50, 75], found);
+    }
+
+    #[test]
+    fn parallel_try_max_by_key_prefers_the_first() {
+        let items = vec![3, -7, 5, 7, 1];
+        let abs = |i: &i32| -> Result<i32, String> { Ok(i.abs()) };
+
+        assert_eq!(Ok(Some((&-7, 7))), parallel_try_max_by_key(&items, abs));
+        assert_eq!(Ok(None), parallel_try_max_by_key(&[], abs));
+    }
+
+    #[test]
+    fn parallel_try_max_by_key_returns_the_first_failure() {
+        let items = vec![3, -7, 5, -8, 1];
+        let positive = |i: &i32| match *i {
+            i if i < 0 => Err(i),
+            i => Ok(i),
+        };
+
+        assert_eq!(Err(-7), parallel_try_max_by_key(&items, positive));
+    }
+}
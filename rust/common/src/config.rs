@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+
+pub const USAGE: &str = "\
+Usage: <binary> [PART] [FILE] [OPTIONS]
+
+Options:
+    -p, --part <part1|part2|both>   Which part to solve (default: both)
+    -i, --input <FILE|->            Read the puzzle input from FILE, or stdin with '-'
+    -e, --example <N>               Solve the Nth example from the puzzle text instead
+    -t, --time                      Report how long each part took
+    -f, --format <text|json>        Output format (default: text)
+    -o, --option <NAME=VALUE>       A setting for the days that read it, such as engine=compiled
+    -h, --help                      Print this message
+
+When no input is given, inputs/dayN.txt is used.";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Part {
+    PartOne,
+    PartTwo,
+    Both,
+}
+
+impl Part {
+    pub fn new(raw_value: String) -> Result<Self, String> {
+        match raw_value.to_lowercase().as_str() {
+            "part1" | "1" => Ok(Part::PartOne),
+            "part2" | "2" => Ok(Part::PartTwo),
+            "both" => Ok(Part::Both),
+            _ => Err(format!("Invalid part: {}", raw_value)),
+        }
+    }
+
+    pub fn parts(self) -> Vec<Part> {
+        match self {
+            Part::Both => vec![Part::PartOne, Part::PartTwo],
+            part => vec![part],
+        }
+    }
+
+    pub fn number(self) -> Option<u8> {
+        match self {
+            Part::PartOne => Some(1),
+            Part::PartTwo => Some(2),
+            Part::Both => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Input {
+    Default,
+    File(String),
+    Stdin,
+    Example(usize),
+}
+
+impl Input {
+    pub fn new(raw_value: String) -> Self {
+        if raw_value == "-" {
+            Input::Stdin
+        } else {
+            Input::File(raw_value)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    pub fn new(raw_value: String) -> Result<Self, String> {
+        match raw_value.to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Invalid format: {}", raw_value)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Config {
+    pub part: Part,
+    pub input: Input,
+    pub time: bool,
+    pub format: Format,
+    pub help: bool,
+    // Settings by name, left for each day to read as it sees fit
+    pub options: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            part: Part::Both,
+            input: Input::Default,
+            time: false,
+            format: Format::Text,
+            help: false,
+            options: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    pub fn new(mut args: std::env::Args) -> Result<Config, String> {
+        args.next();
+
+        Self::from_args(args)
+    }
+
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut positionals = 0;
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| match args.next() {
+                Some(v) => Ok(v),
+                None => Err(format!("Didn't get a value for {}", name)),
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => config.help = true,
+                "-t" | "--time" => config.time = true,
+                "-p" | "--part" => config.part = Part::new(value(&arg)?)?,
+                "-i" | "--input" => config.input = Input::new(value(&arg)?),
+                "-f" | "--format" => config.format = Format::new(value(&arg)?)?,
+                "-o" | "--option" => {
+                    let raw_option = value(&arg)?;
+                    match raw_option.split_once('=') {
+                        Some((name, value)) if !name.is_empty() => {
+                            config.options.insert(name.to_string(), value.to_string());
+                        }
+                        _ => return Err(format!("Invalid option: {}", raw_option)),
+                    }
+                }
+                "-e" | "--example" => {
+                    let raw_example = value(&arg)?;
+                    config.input = match raw_example.parse::<usize>() {
+                        Ok(n) if n > 0 => Input::Example(n),
+                        _ => return Err(format!("Invalid example: {}", raw_example)),
+                    };
+                }
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(format!("Unknown option: {}", arg));
+                }
+                // Positional arguments are kept for the original `<part> <file>` invocation
+                _ => {
+                    match positionals {
+                        0 => config.part = Part::new(arg)?,
+                        1 => config.input = Input::new(arg),
+                        _ => return Err(format!("Unexpected argument: {}", arg)),
+                    }
+
+                    positionals += 1;
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, String> {
+        Config::from_args(args.iter().map(|a| String::from(*a)))
+    }
+
+    #[test]
+    fn positional_part_and_file() {
+        let config = parse(&["part2", "inputs/day7.txt"]).unwrap();
+
+        assert_eq!(Part::PartTwo, config.part);
+        assert_eq!(Input::File(String::from("inputs/day7.txt")), config.input);
+    }
+
+    #[test]
+    fn defaults_to_both_parts_and_default_input() {
+        assert_eq!(Ok(Config::default()), parse(&[]));
+    }
+
+    #[test]
+    fn options() {
+        let config = parse(&[
+            "--part", "both", "--input", "-", "--time", "--format", "json",
+        ])
+        .unwrap();
+
+        assert_eq!(Part::Both, config.part);
+        assert_eq!(Input::Stdin, config.input);
+        assert!(config.time);
+        assert_eq!(Format::Json, config.format);
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(Input::Example(2), parse(&["-e", "2"]).unwrap().input);
+        assert!(parse(&["--example", "0"]).is_err());
+    }
+
+    #[test]
+    fn named_options() {
+        let config = parse(&["-o", "steps=10", "--option", "time=", "-o", "steps=20"]).unwrap();
+
+        assert_eq!(Some("20"), config.option("steps"));
+        assert_eq!(Some(""), config.option("time"));
+        assert_eq!(None, config.option("engine"));
+        assert!(parse(&["-o", "steps"]).is_err());
+        assert!(parse(&["-o", "=1"]).is_err());
+    }
+
+    #[test]
+    fn help() {
+        assert!(parse(&["--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn missing_value_should_fail() {
+        assert!(parse(&["--part"]).is_err());
+    }
+
+    #[test]
+    fn unknown_option_should_fail() {
+        assert!(parse(&["--verbose"]).is_err());
+    }
+}
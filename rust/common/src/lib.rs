@@ -0,0 +1,60 @@
+use std::error::Error;
+
+pub use crate::answer::Answer;
+pub use crate::runner::{default_input, render, run, solve, Failure, Report};
+
+use crate::config::Config;
+
+mod answer;
+pub mod combinatorics;
+pub mod config;
+mod runner;
+
+pub trait Solution {
+    const DAY: u8;
+
+    // Sample inputs from the puzzle text, selectable with `--example N`
+    const EXAMPLES: &'static [&'static str] = &[];
+
+    type Input;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>>;
+
+    // Parses with the config at hand, for days that read settings from it
+    fn parse_with(input: &str, _config: &Config) -> Result<Self::Input, Box<dyn Error>> {
+        Self::parse(input)
+    }
+
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>>;
+
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>>;
+}
+
+// Object-safe view of a `Solution` so the runner can keep every day in one list
+pub trait Day {
+    fn number(&self) -> u8;
+
+    fn examples(&self) -> &'static [&'static str];
+
+    fn solve_part_one(&self, input: &str, config: &Config) -> Result<Answer, Box<dyn Error>>;
+
+    fn solve_part_two(&self, input: &str, config: &Config) -> Result<Answer, Box<dyn Error>>;
+}
+
+impl<S: Solution> Day for S {
+    fn number(&self) -> u8 {
+        S::DAY
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        S::EXAMPLES
+    }
+
+    fn solve_part_one(&self, input: &str, config: &Config) -> Result<Answer, Box<dyn Error>> {
+        S::part_one(&S::parse_with(input, config)?)
+    }
+
+    fn solve_part_two(&self, input: &str, config: &Config) -> Result<Answer, Box<dyn Error>> {
+        S::part_two(&S::parse_with(input, config)?)
+    }
+}
@@ -0,0 +1,304 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, Format, Input, Part, USAGE};
+use crate::{Answer, Day};
+
+#[derive(Debug)]
+pub struct Report {
+    pub day: u8,
+    pub part: Part,
+    pub answer: Answer,
+    pub elapsed: Duration,
+}
+
+// A part that failed, which doesn't keep the other part from being solved
+#[derive(Debug)]
+pub struct Failure {
+    pub day: u8,
+    pub part: Part,
+    pub error: Box<dyn Error>,
+}
+
+pub fn run(day: &dyn Day, config: Config) -> Result<(), Box<dyn Error>> {
+    if config.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let results = solve(day, &config)?;
+    let label = results.len() > 1;
+    let mut failures = 0;
+
+    for result in results {
+        match result {
+            Ok(report) => {
+                let label = if label {
+                    Some(format!("Part {}", report.part.number().unwrap_or(0)))
+                } else {
+                    None
+                };
+
+                println!("{}", render(&report, &config, label));
+            }
+            Err(failure) => {
+                eprintln!(
+                    "Part {} failed: {}",
+                    failure.part.number().unwrap_or(0),
+                    failure.error
+                );
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        return Err(format!("{} part(s) failed", failures).into());
+    }
+
+    Ok(())
+}
+
+// Solves every part asked for, each one whether or not the others fail. Only reading the input
+// fails the lot.
+pub fn solve(
+    day: &dyn Day,
+    config: &Config,
+) -> Result<Vec<Result<Report, Failure>>, Box<dyn Error>> {
+    let contents = read_input(day, &config.input)?;
+    let mut results = Vec::new();
+
+    for part in config.part.parts() {
+        let start = Instant::now();
+        let answer = match part {
+            Part::PartOne => day.solve_part_one(&contents, config),
+            _ => day.solve_part_two(&contents, config),
+        };
+
+        results.push(match answer {
+            Ok(answer) => Ok(Report {
+                day: day.number(),
+                part,
+                answer,
+                elapsed: start.elapsed(),
+            }),
+            Err(error) => Err(Failure {
+                day: day.number(),
+                part,
+                error,
+            }),
+        });
+    }
+
+    Ok(results)
+}
+
+pub fn render(report: &Report, config: &Config, label: Option<String>) -> String {
+    match config.format {
+        Format::Text => render_text(report, config.time, label),
+        Format::Json => render_json(report, config.time),
+    }
+}
+
+fn render_text(report: &Report, time: bool, label: Option<String>) -> String {
+    let elapsed = if time {
+        format!(" ({:.2?})", report.elapsed)
+    } else {
+        String::new()
+    };
+
+    match (label, &report.answer) {
+        (Some(label), Answer::Image(_)) => format!("{}:{}\n{}", label, elapsed, report.answer),
+        (Some(label), _) => format!("{}: {}{}", label, report.answer, elapsed),
+        (None, Answer::Image(_)) if time => format!("{}\n{}", elapsed.trim(), report.answer),
+        (None, _) => format!("{}{}", report.answer, elapsed),
+    }
+}
+
+fn render_json(report: &Report, time: bool) -> String {
+    let answer = match &report.answer {
+        Answer::Integer(i) => i.to_string(),
+        Answer::Text(s) => json_string(s),
+        Answer::Image(rows) => format!(
+            "[{}]",
+            rows.iter()
+                .map(|r| json_string(r))
+                .collect::<Vec<String>>()
+                .join(",")
+        ),
+        Answer::Unsolved => String::from("null"),
+    };
+
+    let elapsed = if time {
+        format!(
+            ",\"elapsed_ms\":{:.3}",
+            report.elapsed.as_secs_f64() * 1000.0
+        )
+    } else {
+        String::new()
+    };
+
+    format!(
+        "{{\"day\":{},\"part\":{},\"answer\":{}{}}}",
+        report.day,
+        report.part.number().unwrap_or(0),
+        answer,
+        elapsed
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+fn read_input(day: &dyn Day, input: &Input) -> Result<String, Box<dyn Error>> {
+    match input {
+        Input::Default => Ok(fs::read_to_string(default_input(day.number()))?),
+        Input::File(filename) => Ok(fs::read_to_string(filename)?),
+        Input::Stdin => {
+            let mut contents = String::new();
+            io::stdin().read_to_string(&mut contents)?;
+            Ok(contents)
+        }
+        Input::Example(n) => match day.examples().get(n - 1) {
+            Some(example) => Ok(String::from(*example)),
+            None => Err(format!("Day {} has no example {}", day.number(), n).into()),
+        },
+    }
+}
+
+// Inputs live at the repository root, so look there too when running from the workspace
+pub fn default_input(day: u8) -> String {
+    let candidates = [
+        format!("inputs/day{}.txt", day),
+        format!("../inputs/day{}.txt", day),
+    ];
+
+    candidates
+        .iter()
+        .find(|c| Path::new(c).exists())
+        .unwrap_or(&candidates[0])
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solution;
+
+    struct HalfDone;
+
+    impl Solution for HalfDone {
+        const DAY: u8 = 99;
+        const EXAMPLES: &'static [&'static str] = &["21"];
+
+        type Input = i64;
+
+        fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+            Ok(input.parse()?)
+        }
+
+        fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+            Ok((input * 2).into())
+        }
+
+        fn part_two(_input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+            Err("Out of ideas".into())
+        }
+    }
+
+    fn report(answer: Answer) -> Report {
+        Report {
+            day: 8,
+            part: Part::PartTwo,
+            answer,
+            elapsed: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    fn a_failing_part_keeps_the_other() {
+        let config = Config {
+            input: Input::Example(1),
+            ..Config::default()
+        };
+        let results = solve(&HalfDone, &config).unwrap();
+
+        assert_eq!(2, results.len());
+        assert_eq!(Answer::Integer(42), results[0].as_ref().unwrap().answer);
+        let failure = results[1].as_ref().unwrap_err();
+        assert_eq!(Part::PartTwo, failure.part);
+        assert_eq!("Out of ideas", failure.error.to_string());
+    }
+
+    #[test]
+    fn render_text_without_label() {
+        let config = Config::default();
+
+        assert_eq!("42", render(&report(Answer::Integer(42)), &config, None));
+    }
+
+    #[test]
+    fn render_text_with_label_and_time() {
+        let config = Config {
+            time: true,
+            ..Config::default()
+        };
+        let label = Some(String::from("Part 2"));
+
+        assert_eq!(
+            "Part 2: 42 (5.00ms)",
+            render(&report(Answer::Integer(42)), &config, label)
+        );
+    }
+
+    #[test]
+    fn render_json_image() {
+        let config = Config {
+            format: Format::Json,
+            ..Config::default()
+        };
+        let image = Answer::Image(vec![String::from(" *"), String::from("\"\\")]);
+
+        assert_eq!(
+            "{\"day\":8,\"part\":2,\"answer\":[\" *\",\"\\\"\\\\\"]}",
+            render(&report(image), &config, None)
+        );
+    }
+
+    #[test]
+    fn render_unsolved() {
+        let json = Config {
+            format: Format::Json,
+            ..Config::default()
+        };
+        let label = Some(String::from("Part 2"));
+
+        assert_eq!(
+            "Part 2: not solved yet",
+            render(&report(Answer::Unsolved), &Config::default(), label)
+        );
+        assert_eq!(
+            "{\"day\":8,\"part\":2,\"answer\":null}",
+            render(&report(Answer::Unsolved), &json, None)
+        );
+    }
+}
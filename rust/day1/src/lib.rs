@@ -1,10 +1,9 @@
 use std::cmp::max;
 use std::error::Error;
-use std::fs;
 
-pub mod config;
+use common::{Answer, Solution};
 
-struct Module {
+pub struct Module {
     mass: i32,
 }
 
@@ -35,22 +34,26 @@ impl Module {
     }
 }
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.filename)?;
-    let modules = parse_modules(&contents);
+pub struct Day1;
 
-    match config.part {
-        config::Part::Part1 => {
-            let result = part1(&modules);
-            println!("{}", result);
-        }
-        config::Part::Part2 => {
-            let result = part2(&modules);
-            println!("{}", result);
-        }
+impl Solution for Day1 {
+    const DAY: u8 = 1;
+
+    const EXAMPLES: &'static [&'static str] = &["12", "14", "1969", "100756"];
+
+    type Input = Vec<Module>;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_modules(input))
+    }
+
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part1(input).into())
     }
 
-    Ok(())
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part2(input).into())
+    }
 }
 
 fn parse_modules(contents: &str) -> Vec<Module> {
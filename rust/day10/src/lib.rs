@@ -1,11 +1,11 @@
 use std::error::Error;
-use std::fs;
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use core::fmt;
 use std::iter::FromIterator;
 
-pub mod config;
+use common::{Answer, Solution};
+
 pub mod point;
 
 #[derive(Debug, Hash, Eq, PartialEq)]
@@ -24,7 +24,7 @@ impl Position {
 }
 
 #[derive(Debug)]
-struct Map {
+pub struct Map {
     positions: HashMap<(isize, isize), Position>,
 }
 
@@ -105,21 +105,30 @@ impl Display for Map {
     }
 }
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let input = fs::read_to_string(config.filename)?;
+pub struct Day10;
 
-    match config.part {
-        config::Part::PartOne => {
-            let map = parse_input(&input);
-            let result = part_one(&map);
-            println!("{}", result);
-        }
-        config::Part::PartTwo => {
-            // TODO: Part two
-        }
+impl Solution for Day10 {
+    const DAY: u8 = 10;
+
+    const EXAMPLES: &'static [&'static str] = &[
+        ".#..#\n.....\n#####\n....#\n...##",
+        "......#.#.\n#..#.#....\n..#######.\n.#.#.###..\n.#..#.....\n..#....#.#\n#..#....#.\n.##.#..###\n##...#..#.\n.#....####",
+        "#.#...#.#.\n.###....#.\n.#....#...\n##.#.#.#.#\n....#.#.#.\n.##..###.#\n..#...##..\n..##....##\n......#...\n.####.###.",
+    ];
+
+    type Input = Map;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_input(input))
+    }
+
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_one(input).into())
     }
 
-    Ok(())
+    fn part_two(_input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(Answer::Unsolved)
+    }
 }
 
 fn parse_input(input: &str) -> Map {
@@ -1,43 +1,52 @@
-
 use std::error::Error;
-use std::fs;
+use std::num::ParseIntError;
+use std::ops::Range;
 
-pub mod config;
+use common::combinatorics::{cartesian_product, parallel_search};
+use common::config::Config;
+use common::{Answer, Solution};
+use intcode::{IntcodeError, Program};
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.filename)?;
-    let intcode = parse_intcode(&contents);
+pub struct Day2;
 
-    match config.part {
-        config::Part::Part1 => {
-            let result = part1(&intcode);
-            println!("{}", result);
-        }
-        config::Part::Part2 => {
-            let result = part2(&intcode, 19690720);
-            println!("{:?}", result);
-        }
+impl Solution for Day2 {
+    const DAY: u8 = 2;
+
+    type Input = Program;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(Program::new(parse_intcode(input)?))
+    }
+
+    fn parse_with(input: &str, config: &Config) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(Self::parse(input)?.settings(|name| config.option(name))?)
+    }
+
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part1(input)?.into())
     }
 
-    Ok(())
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        match part2(input, 19690720) {
+            Some(result) => Ok(result.into()),
+            None => Err("No noun and verb produce 19690720".into()),
+        }
+    }
 }
 
-fn parse_intcode(contents: &str) -> Vec<usize> {
-    contents
-        .split(",")
-        .map(|s| s.trim().parse::<usize>().unwrap())
-        .collect()
+fn parse_intcode(contents: &str) -> Result<Vec<i64>, ParseIntError> {
+    intcode::parse_program(contents)
 }
 
-fn part1(intcode: &Vec<usize>) -> usize {
+fn part1(intcode: &Program) -> Result<i64, IntcodeError> {
     let clone = intcode_with_parameters(intcode, 12, 2);
-    let result = run_intcode(&clone);
+    let result = run_intcode(&clone)?;
 
-    result[0]
+    Ok(result[0])
 }
 
-fn intcode_with_parameters(intcode: &Vec<usize>, noun: usize, verb: usize) -> Vec<usize> {
-    let mut clone = intcode.clone();
+fn intcode_with_parameters(intcode: &Program, noun: i64, verb: i64) -> Program {
+    let mut clone = intcode.code().to_vec();
 
     if clone.len() > 1 {
         clone[1] = noun;
@@ -47,60 +56,86 @@ fn intcode_with_parameters(intcode: &Vec<usize>, noun: usize, verb: usize) -> Ve
         clone[2] = verb;
     }
 
-    clone
+    intcode.with_code(clone)
 }
 
-fn run_intcode(intcode: &Vec<usize>) -> Vec<usize> {
-    let mut intcode = intcode.clone();
-
-    let get_indexes = |i: usize, intcode: &Vec<usize>| {
-        let x_index = intcode[i + 1];
-        let y_index = intcode[i + 2];
-        let result_index = intcode[i + 3];
-
-        (x_index, y_index, result_index)
-    };
-
-    let mut i = 0;
-    loop {
-        let value = intcode[i];
-        match value {
-            1 => {
-                let (x_index, y_index, result_index) = get_indexes(i, &intcode);
-                intcode[result_index] = intcode[x_index] + intcode[y_index];
-                i += 4;
-            },
-            2 => {
-                let (x_index, y_index, result_index) = get_indexes(i, &intcode);
-                intcode[result_index] = intcode[x_index] * intcode[y_index];
-                i += 4;
-            },
-            99 => {
-                break;
-            },
-            _ => {
-                i += 1;
-            }
-        };
-    }
+fn run_intcode(intcode: &Program) -> Result<Vec<i64>, IntcodeError> {
+    let mut machine = intcode.machine();
+    machine.run_to_halt()?;
+
+    Ok(machine.current_state().to_vec())
+}
 
-    intcode
+fn part2(intcode: &Program, expected_output: i64) -> Option<i64> {
+    NounVerbSearch::new(intcode, expected_output)
+        .first()
+        .map(|(noun, verb)| 100 * noun + verb)
 }
 
-// TODO: Do it in parallel
-fn part2(intcode: &Vec<usize>, expected_output: usize) -> Option<usize> {
-    for noun in 0..100 {
-        for verb in 0..100 {
-            let clone = intcode_with_parameters(intcode, noun, verb);
-            let result = run_intcode(&clone);
+// Looks for the values that, written to two addresses of the program, make it halt with
+// `target` at address 0
+pub struct NounVerbSearch<'a> {
+    program: &'a Program,
+    target: i64,
+    noun: (usize, Range<i64>),
+    verb: (usize, Range<i64>),
+}
 
-            if result[0] == expected_output {
-                return Some(100 * noun + verb);
-            }
+impl<'a> NounVerbSearch<'a> {
+    // The puzzle's search: nouns and verbs from 0 to 99, at addresses 1 and 2
+    pub fn new(program: &'a Program, target: i64) -> Self {
+        Self {
+            program,
+            target,
+            noun: (1, 0..100),
+            verb: (2, 0..100),
         }
     }
 
-    None
+    pub fn noun(mut self, address: usize, values: Range<i64>) -> Self {
+        self.noun = (address, values);
+        self
+    }
+
+    pub fn verb(mut self, address: usize, values: Range<i64>) -> Self {
+        self.verb = (address, values);
+        self
+    }
+
+    // The match with the smallest noun, then the smallest verb
+    pub fn first(&self) -> Option<(i64, i64)> {
+        self.search(true).into_iter().next()
+    }
+
+    pub fn all(&self) -> Vec<(i64, i64)> {
+        self.search(false)
+    }
+
+    fn search(&self, first_only: bool) -> Vec<(i64, i64)> {
+        let pairs =
+            cartesian_product(&[self.noun.1.clone().collect(), self.verb.1.clone().collect()]);
+        let pairs = pairs
+            .map(|pair| (pair[0], pair[1]))
+            .collect::<Vec<(i64, i64)>>();
+
+        // Each thread resets its own machine instead of copying the program for every pair
+        parallel_search(
+            &pairs,
+            first_only,
+            || self.program.machine(),
+            |machine, &(noun, verb)| {
+                machine.reset();
+                machine.set_memory(self.noun.0, noun).ok()?;
+                machine.set_memory(self.verb.0, verb).ok()?;
+
+                // Some noun/verb pairs produce programs that don't run, those can't be the answer
+                match machine.run_to_halt() {
+                    Ok(_) if machine.memory(0) == self.target => Some((noun, verb)),
+                    _ => None,
+                }
+            },
+        )
+    }
 }
 
 #[cfg(test)]
@@ -111,53 +146,77 @@ mod tests {
     fn parse() {
         let contents = "1,2,3,4, 5";
 
-        let expected = vec![1usize, 2, 3, 4, 5];
+        let expected = vec![1i64, 2, 3, 4, 5];
 
-        assert_eq!(expected, parse_intcode(&contents));
+        assert_eq!(Ok(expected), parse_intcode(contents));
     }
 
     #[test]
     fn run_intcode_example1() {
-        let intcode = vec![1usize, 0usize, 0usize, 0usize, 99usize];
+        let intcode = Program::new(vec![1i64, 0, 0, 0, 99]);
 
-        let expected = vec![2usize, 0, 0, 0, 99];
+        let expected = vec![2i64, 0, 0, 0, 99];
 
-        assert_eq!(expected, run_intcode(&intcode));
+        assert_eq!(Ok(expected), run_intcode(&intcode));
     }
 
     #[test]
     fn run_intcode_example2() {
-        let intcode = vec![2usize, 3, 0, 3, 99];
+        let intcode = Program::new(vec![2i64, 3, 0, 3, 99]);
 
-        let expected = vec![2usize, 3, 0, 6, 99];
+        let expected = vec![2i64, 3, 0, 6, 99];
 
-        assert_eq!(expected, run_intcode(&intcode));
+        assert_eq!(Ok(expected), run_intcode(&intcode));
     }
 
     #[test]
     fn run_intcode_example3() {
-        let intcode = vec![2usize, 4, 4, 5, 99, 0];
+        let intcode = Program::new(vec![2i64, 4, 4, 5, 99, 0]);
 
-        let expected = vec![2usize, 4, 4, 5, 99, 9801];
+        let expected = vec![2i64, 4, 4, 5, 99, 9801];
 
-        assert_eq!(expected, run_intcode(&intcode));
+        assert_eq!(Ok(expected), run_intcode(&intcode));
     }
 
     #[test]
     fn run_intcode_example4() {
-        let intcode = vec![1usize, 1, 1, 4, 99, 5, 6, 0, 99];
+        let intcode = Program::new(vec![1i64, 1, 1, 4, 99, 5, 6, 0, 99]);
 
-        let expected = vec![30usize, 1, 1, 4, 2, 5, 6, 0, 99];
+        let expected = vec![30i64, 1, 1, 4, 2, 5, 6, 0, 99];
 
-        assert_eq!(expected, run_intcode(&intcode));
+        assert_eq!(Ok(expected), run_intcode(&intcode));
     }
 
     #[test]
     fn run_intcode_example5() {
-        let intcode = vec![1usize, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        let intcode = Program::new(vec![1i64, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
 
         let expected = vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50];
 
-        assert_eq!(expected, run_intcode(&intcode));
+        assert_eq!(Ok(expected), run_intcode(&intcode));
+    }
+
+    #[test]
+    fn search_all_pairs() {
+        // Adds the values at the noun and verb addresses
+        let intcode = Program::new(vec![1, 0, 0, 0, 99, 10, 20, 30]);
+        let search = NounVerbSearch::new(&intcode, 40)
+            .noun(1, 5..8)
+            .verb(2, 5..8);
+
+        assert_eq!(vec![(5, 7), (6, 6), (7, 5)], search.all());
+        assert_eq!(Some((5, 7)), search.first());
+        assert_eq!(None, search.verb(2, 5..6).noun(1, 5..6).first());
+    }
+
+    #[test]
+    fn search_other_addresses() {
+        // Multiplies the values at addresses 5 and 6, the noun and verb themselves
+        let intcode = Program::new(vec![2, 5, 6, 0, 99, 0, 0]);
+        let search = NounVerbSearch::new(&intcode, 12)
+            .noun(5, 0..10)
+            .verb(6, 0..10);
+
+        assert_eq!(vec![(2, 6), (3, 4), (4, 3), (6, 2)], search.all());
     }
 }
@@ -1,15 +1,12 @@
 use std::collections::HashSet;
 use std::error::Error;
-use std::fs;
 use std::iter::FromIterator;
 use std::str::FromStr;
 
-use crate::config::Part;
-
-pub mod config;
+use common::{Answer, Solution};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
-struct Point {
+pub struct Point {
     x: i32,
     y: i32,
 }
@@ -96,7 +93,7 @@ impl FromStr for Movement {
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
-struct Wire {
+pub struct Wire {
     current_position: Point,
     path: Vec<Point>,
 }
@@ -130,22 +127,30 @@ impl Wire {
     }
 }
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.filename)?;
-    let input = parse_input(&contents);
-
-    match config.part {
-        Part::Part1 => {
-            let result = part1(&input);
-            println!("{:?}", result);
-        },
-        Part::Part2 => {
-            let result = part2(&input);
-            println!("{:?}", result);
-        }
+pub struct Day3;
+
+impl Solution for Day3 {
+    const DAY: u8 = 3;
+
+    const EXAMPLES: &'static [&'static str] = &[
+        "R8,U5,L5,D3\nU7,R6,D4,L4",
+        "R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83",
+        "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+    ];
+
+    type Input = (Point, Vec<Wire>);
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_input(input))
+    }
+
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part1(input).into())
     }
 
-    Ok(())
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part2(input).into())
+    }
 }
 
 fn parse_input(input: &str) -> (Point, Vec<Wire>) {
@@ -1,28 +1,29 @@
 use std::error::Error;
-use std::fs;
 use std::ops::RangeInclusive;
 use std::collections::HashMap;
 
-pub mod config;
+use common::{Answer, Solution};
 
 type Password = String;
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.filename)?;
-    let range = parse_input(&contents);
+pub struct Day4;
 
-    match config.part {
-        config::Part::PartOne => {
-            let count = part_one(range);
-            println!("{}", count);
-        }
-        config::Part::PartTwo => {
-            let count = part_two(range);
-            println!("{}", count);
-        }
+impl Solution for Day4 {
+    const DAY: u8 = 4;
+
+    type Input = (i32, i32);
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_input(input))
     }
 
-    Ok(())
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_one(*input).into())
+    }
+
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_two(*input).into())
+    }
 }
 
 fn parse_input(contents: &str) -> (i32, i32) {
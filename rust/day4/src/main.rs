@@ -1,6 +1,6 @@
 use std::{env, process};
 
-use day4::config;
+use common::config;
 
 fn main() {
     let config = config::Config::new(env::args()).unwrap_or_else(|err| {
@@ -9,7 +9,7 @@ fn main() {
         process::exit(1);
     });
 
-    if let Err(e) = day4::run(config) {
+    if let Err(e) = common::run(&day4::Day4, config) {
         eprintln!("Application error: {}", e);
 
         process::exit(1);
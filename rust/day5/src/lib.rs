@@ -1,293 +1,152 @@
 use std::error::Error;
-use std::fs;
 
-pub mod config;
+use common::config::Config;
+use common::{Answer, Solution};
+use intcode::{IntcodeError, Program};
 
-#[derive(Debug, PartialEq)]
-enum ParameterMode {
-    Position,
-    Immediate,
-}
+pub struct Day5;
 
-#[derive(Debug, PartialEq)]
-enum Opcode {
-    Add,
-    Multiply,
-    Set,
-    Output,
-    JumpIfTrue,
-    JumpIfFalse,
-    LessThan,
-    Equals,
-    Halt,
-}
+impl Solution for Day5 {
+    const DAY: u8 = 5;
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let program: Vec<i32> = fs::read_to_string(config.filename)?
-        .split(",")
-        .map(|s| s.trim().parse::<i32>().unwrap())
-        .collect();
+    type Input = Program;
 
-    match config.part {
-        config::Part::PartOne => {
-            let result = part_one(&program)?;
-            println!("{:?}", result);
-        }
-        config::Part::PartTwo => {
-            let result = part_two(&program)?;
-            println!("{:?}", result);
-        }
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(Program::new(intcode::parse_program(input)?))
     }
 
-    Ok(())
-}
-
-fn part_one(program: &Vec<i32>) -> Result<Option<i32>, String> {
-    match compute(program, 1) {
-        Ok((output, _)) => Ok(output),
-        Err(s) => Err(s),
+    fn parse_with(input: &str, config: &Config) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(Self::parse(input)?.settings(|name| config.option(name))?)
     }
-}
 
-fn part_two(program: &Vec<i32>) -> Result<Option<i32>, String> {
-    match compute(program, 5) {
-        Ok((output, _)) => Ok(output),
-        Err(s) => Err(s),
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_one(input)?.into())
     }
-}
 
-fn compute(program: &Vec<i32>, input: i32) -> Result<(Option<i32>, Vec<i32>), String> {
-    let mut program = program.clone();
-    let mut i = 0;
-    let mut output = None;
-
-    while i < program.len() {
-        let operation = program[i];
-        let (opcode, first_mode, second_mode, _third_mode) = parse_operation(operation)?;
-
-        match opcode {
-            Opcode::Add => {
-                let first_parameter = get_parameter(i + 1, &first_mode, &program);
-                let second_parameter = get_parameter(i + 2, &second_mode, &program);
-                let result_index = program[i + 3] as usize;
-
-                program[result_index] = first_parameter + second_parameter;
-
-                i += 4;
-            },
-            Opcode::Multiply => {
-                let first_parameter = get_parameter(i + 1, &first_mode, &program);
-                let second_parameter = get_parameter(i + 2, &second_mode, &program);
-                let result_index = program[i + 3] as usize;
-
-                program[result_index] = first_parameter * second_parameter;
-
-                i += 4;
-            },
-            Opcode::Set => {
-                let result_index = program[i + 1] as usize;
-
-                program[result_index] = input;
-
-                i += 2;
-            },
-            Opcode::Output => {
-                let value = match first_mode {
-                    ParameterMode::Position => {
-                        let index = program[i + 1] as usize;
-                        program[index]
-                    },
-                    ParameterMode::Immediate => {
-                        program[i + 1]
-                    }
-                };
-
-                output = Some(value);
-
-                i += 2;
-            },
-            Opcode::JumpIfTrue => {
-                let first_parameter = get_parameter(i + 1, &first_mode, &program);
-                let second_parameter = get_parameter(i + 2, &second_mode, &program);
-
-                if first_parameter != 0 {
-                    i = second_parameter as usize;
-                } else {
-                    i += 3;
-                }
-            },
-            Opcode::JumpIfFalse => {
-                let first_parameter = get_parameter(i + 1, &first_mode, &program);
-                let second_parameter = get_parameter(i + 2, &second_mode, &program);
-
-                if first_parameter == 0 {
-                    i = second_parameter as usize;
-                } else {
-                    i += 3;
-                }
-            },
-            Opcode::LessThan => {
-                let first_parameter = get_parameter(i + 1, &first_mode, &program);
-                let second_parameter = get_parameter(i + 2, &second_mode, &program);
-                let result_index = program[i + 3] as usize;
-
-                let value = if first_parameter < second_parameter {
-                    1
-                } else {
-                    0
-                };
-
-                program[result_index] = value;
-
-                i += 4;
-            },
-            Opcode::Equals => {
-                let first_parameter = get_parameter(i + 1, &first_mode, &program);
-                let second_parameter = get_parameter(i + 2, &second_mode, &program);
-                let result_index = program[i + 3] as usize;
-
-                let value = if first_parameter == second_parameter {
-                    1
-                } else {
-                    0
-                };
-
-                program[result_index] = value;
-
-                i += 4;
-            },
-            Opcode::Halt => {
-                break;
-            },
-        }
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_two(input)?.into())
     }
-
-    Ok((output, program))
 }
 
-fn parse_operation(operation: i32) -> Result<(Opcode, ParameterMode, ParameterMode, ParameterMode), String> {
-    let opcode = parse_opcode(operation % 100)?;
-    let first_parameter_mode = parse_mode(operation / 100 % 10)?;
-    let second_parameter_mode = parse_mode(operation / 1000 % 10)?;
-    let third_parameter_mode = parse_mode(operation / 10000 % 10)?;
-
-    Ok((opcode, first_parameter_mode, second_parameter_mode, third_parameter_mode))
+fn part_one(program: &Program) -> Result<i64, IntcodeError> {
+    let (output, _) = compute(program, 1)?;
+    output.ok_or(IntcodeError::NoOutput)
 }
 
-fn parse_opcode(opcode: i32) -> Result<Opcode, String> {
-    match opcode % 100 {
-        1 => Ok(Opcode::Add),
-        2 => Ok(Opcode::Multiply),
-        3 => Ok(Opcode::Set),
-        4 => Ok(Opcode::Output),
-        5 => Ok(Opcode::JumpIfTrue),
-        6 => Ok(Opcode::JumpIfFalse),
-        7 => Ok(Opcode::LessThan),
-        8 => Ok(Opcode::Equals),
-        99 => Ok(Opcode::Halt),
-        _ => Err(format!("Invalid opcode: {}", opcode))
-    }
+fn part_two(program: &Program) -> Result<i64, IntcodeError> {
+    let (output, _) = compute(program, 5)?;
+    output.ok_or(IntcodeError::NoOutput)
 }
 
-fn parse_mode(mode: i32) -> Result<ParameterMode, String> {
-    match mode {
-        0 => Ok(ParameterMode::Position),
-        1 => Ok(ParameterMode::Immediate),
-        _ => Err(format!("Invalid param mode: {}", mode)),
-    }
-}
+fn compute(program: &Program, input: i64) -> Result<(Option<i64>, Vec<i64>), IntcodeError> {
+    let mut intcode = program.machine();
+    intcode.push_input(input);
+    intcode.run_to_halt()?;
 
-fn get_parameter(index: usize, mode: &ParameterMode, program: &Vec<i32>) -> i32 {
-    match mode {
-        ParameterMode::Position => {
-            let index = program[index] as usize;
-            program[index]
-        },
-        ParameterMode::Immediate => {
-            program[index]
-        },
-    }
+    Ok((intcode.last_output(), intcode.current_state().to_vec()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    #[test]
-    fn parse_operation_test() {
-        let operation = 1002;
-        assert_eq!(
-            Ok((Opcode::Multiply, ParameterMode::Position, ParameterMode::Immediate, ParameterMode::Position)),
-            parse_operation(operation));
-    }
+    use intcode::Engine;
 
     #[test]
     fn compute_test_1() {
-        let program = vec![1101, 100, -1, 4, 0];
-        let (_, result) = compute(&program, 1).unwrap();
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![1101, 100, -1, 4, 0]).engine(engine);
+            let (_, result) = compute(&program, 1).unwrap();
 
-        let expected = vec![1101, 100, -1, 4, 99];
+            let expected = vec![1101, 100, -1, 4, 99];
 
-        assert_eq!(expected, result);
+            assert_eq!(expected, result);
+        }
     }
 
     #[test]
     fn compute_test_2() {
-        let program = vec![1, 0, 0, 0, 99];
-        let (_, result) = compute(&program, 1).unwrap();
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![1, 0, 0, 0, 99]).engine(engine);
+            let (_, result) = compute(&program, 1).unwrap();
 
-        let expected = vec![2, 0, 0, 0, 99];
+            let expected = vec![2, 0, 0, 0, 99];
 
-        assert_eq!(expected, result);
+            assert_eq!(expected, result);
+        }
     }
 
     #[test]
     fn compute_test_3() {
-        let program = vec![2, 3, 0, 3, 99];
-        let (_, result) = compute(&program, 1).unwrap();
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![2, 3, 0, 3, 99]).engine(engine);
+            let (_, result) = compute(&program, 1).unwrap();
 
-        let expected = vec![2, 3, 0, 6, 99];
+            let expected = vec![2, 3, 0, 6, 99];
 
-        assert_eq!(expected, result);
+            assert_eq!(expected, result);
+        }
     }
 
     #[test]
     fn compute_test_4() {
-        let program = vec![2, 4, 4, 5, 99, 0];
-        let (_, result) = compute(&program, 1).unwrap();
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![2, 4, 4, 5, 99, 0]).engine(engine);
+            let (_, result) = compute(&program, 1).unwrap();
 
-        let expected = vec![2, 4, 4, 5, 99, 9801];
+            let expected = vec![2, 4, 4, 5, 99, 9801];
 
-        assert_eq!(expected, result);
+            assert_eq!(expected, result);
+        }
     }
 
     #[test]
     fn compute_test_5() {
-        let program = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
-        let (_, result) = compute(&program, 1).unwrap();
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![1, 1, 1, 4, 99, 5, 6, 0, 99]).engine(engine);
+            let (_, result) = compute(&program, 1).unwrap();
 
-        let expected = vec![30, 1, 1, 4, 2, 5, 6, 0, 99];
+            let expected = vec![30, 1, 1, 4, 2, 5, 6, 0, 99];
 
-        assert_eq!(expected, result);
+            assert_eq!(expected, result);
+        }
     }
 
     #[test]
     fn compute_test_6() {
-        let program = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
-        let (_, result) = compute(&program, 1).unwrap();
+        for &engine in Engine::ALL.iter() {
+            let program =
+                Program::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]).engine(engine);
+            let (_, result) = compute(&program, 1).unwrap();
 
-        let expected = vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50];
+            let expected = vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50];
 
-        assert_eq!(expected, result);
+            assert_eq!(expected, result);
+        }
     }
 
     #[test]
     fn compute_test_7() {
-        let program = vec![1002, 4, 3, 4, 33];
-        let (_, result) = compute(&program, 1).unwrap();
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![1002, 4, 3, 4, 33]).engine(engine);
+            let (_, result) = compute(&program, 1).unwrap();
+
+            let expected = vec![1002, 4, 3, 4, 99];
 
-        let expected = vec![1002, 4, 3, 4, 99];
+            assert_eq!(expected, result);
+        }
+    }
 
-        assert_eq!(expected, result);
+    #[test]
+    fn machines_run_with_the_configured_limits() {
+        let mut config = Config::default();
+        config
+            .options
+            .insert(String::from("steps"), String::from("1"));
+        let program = Day5::parse_with("1101,100,-1,4,0,99", &config).unwrap();
+
+        assert_eq!(
+            Err(IntcodeError::StepLimitExceeded { limit: 1 }),
+            part_one(&program)
+        );
     }
 }
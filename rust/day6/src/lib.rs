@@ -1,27 +1,31 @@
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::fs;
 
-pub mod config;
+use common::{Answer, Solution};
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let input = fs::read_to_string(config.filename)?;
+pub struct Day6;
 
+impl Solution for Day6 {
+    const DAY: u8 = 6;
 
-    match config.part {
-        config::Part::PartOne => {
-            let orbits = parse_orbits_part_one(&input);
-            let result = part_one(&orbits);
-            println!("{}", result);
-        }
-        config::Part::PartTwo => {
-            let orbits = parse_orbits_part_two(&input);
-            let result = part_two(&orbits);
-            println!("{}", result);
-        }
+    const EXAMPLES: &'static [&'static str] = &[
+        "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L",
+        "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN",
+    ];
+
+    type Input = (HashMap<String, Vec<String>>, HashMap<String, String>);
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok((parse_orbits_part_one(input), parse_orbits_part_two(input)))
     }
 
-    Ok(())
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_one(&input.0).into())
+    }
+
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_two(&input.1).into())
+    }
 }
 
 fn parse_orbits_part_one(input: &str) -> HashMap<String, Vec<String>> {
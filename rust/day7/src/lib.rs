@@ -1,234 +1,242 @@
-use std::collections::HashSet;
 use std::error::Error;
-use std::fs;
-use std::iter::FromIterator;
 
-use crate::intcode::Intcode;
+use common::combinatorics::{parallel_try_max_by_key, permutations};
+use common::config::Config;
+use common::{Answer, Solution};
+use intcode::{IntcodeError, Network, Program};
 
-pub mod config;
-pub mod intcode;
+pub struct Day7;
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let program: Vec<i32> = fs::read_to_string(config.filename)?
-        .split(",")
-        .map(|s| s.trim().parse::<i32>().unwrap())
-        .collect();
+impl Solution for Day7 {
+    const DAY: u8 = 7;
 
-    match config.part {
-        config::Part::PartOne => {
-            let result = part_one(&program)?;
-            println!("{:?}", result);
-        }
-        config::Part::PartTwo => {
-            let result = part_two(&program)?;
-            println!("{:?}", result);
-        }
-    }
+    const EXAMPLES: &'static [&'static str] = &[
+        "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0",
+        "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0",
+        "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0",
+        "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        "3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10",
+    ];
 
-    Ok(())
-}
+    type Input = Program;
 
-fn part_one(program: &Vec<i32>) -> Result<i32, String> {
-    let mut max_signal = 0;
-
-    for a in 0..=4 {
-        for b in 0..=4 {
-            for c in 0..=4 {
-                for d in 0..=4 {
-                    for e in 0..=4 {
-                        let phase_settings = vec![a, b, c, d, e];
-                        // Ensure values are distinct
-                        // TODO: Surely there's a better way to do this
-                        let phase_set: HashSet<&i32> = HashSet::from_iter(phase_settings.iter());
-                        if phase_set.len() != phase_settings.len() {
-                            continue;
-                        }
-
-                        let result = calculate_signal(program, &phase_settings)?;
-                        if result > max_signal {
-                            max_signal = result;
-                        }
-                    }
-                }
-            }
-        }
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(Program::new(intcode::parse_program(input)?))
     }
 
-    Ok(max_signal)
-}
+    fn parse_with(input: &str, config: &Config) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(Self::parse(input)?.settings(|name| config.option(name))?)
+    }
 
-fn calculate_signal(program: &Vec<i32>, phase_settings: &Vec<i32>) -> Result<i32, String> {
-    let mut current_input = 0;
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_one(input)?.into())
+    }
 
-    for setting in phase_settings {
-        let mut intcode = Intcode::new(program);
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_two(input)?.into())
+    }
+}
 
-        intcode.set_input(setting.clone());
+fn part_one(program: &Program) -> Result<i64, IntcodeError> {
+    max_signal(program, &[0, 1, 2, 3, 4], calculate_signal)
+}
 
-        while !intcode.halted() {
-            intcode.compute()?;
-            intcode.set_input(current_input);
-        }
+fn calculate_signal(program: &Program, phase_settings: &[i64]) -> Result<i64, IntcodeError> {
+    let (mut network, names) = amplifiers(program, phase_settings);
+    let names = names.iter().map(String::as_str).collect::<Vec<&str>>();
+    network.chain(&names)?.input(names[0], 0)?.run()?;
 
-        current_input = match intcode.last_output() {
-            Some(o) => o,
-            None => current_input,
-        };
-    }
+    network
+        .last_output(names[names.len() - 1])
+        .ok_or(IntcodeError::NoOutput)
+}
 
-    Ok(current_input)
+fn part_two(program: &Program) -> Result<i64, IntcodeError> {
+    max_signal(program, &[5, 6, 7, 8, 9], calculate_signal_feedback)
 }
 
-fn part_two(program: &Vec<i32>) -> Result<i32, String> {
-    let mut max_signal = 0;
-
-    for a in 5..=9 {
-        for b in 5..=9 {
-            for c in 5..=9 {
-                for d in 5..=9 {
-                    for e in 5..=9 {
-                        let phase_settings = vec![a, b, c, d, e];
-                        // Ensure values are distinct
-                        // TODO: Surely there's a better way to do this
-                        let phase_set: HashSet<&i32> = HashSet::from_iter(phase_settings.iter());
-                        if phase_set.len() != phase_settings.len() {
-                            continue;
-                        }
-
-                        let result = calculate_signal_feedback(program, &phase_settings)?;
-                        if result > max_signal {
-                            max_signal = result;
-                        }
-                    }
-                }
-            }
-        }
-    }
+// Tries every order of the phase settings, spread over every core
+fn max_signal<F>(program: &Program, phases: &[i64], signal: F) -> Result<i64, IntcodeError>
+where
+    F: Fn(&Program, &[i64]) -> Result<i64, IntcodeError> + Sync,
+{
+    let orders = permutations(phases).collect::<Vec<Vec<i64>>>();
+
+    let best = parallel_try_max_by_key(&orders, |order| signal(program, order))?;
 
-    Ok(max_signal)
+    Ok(best.map_or(0, |(_, signal)| signal))
 }
 
-fn calculate_signal_feedback(program: &Vec<i32>, phase_settings: &Vec<i32>) -> Result<i32, String> {
-    let mut intcodes = phase_settings
-        .iter()
-        .map(|s| {
-            let mut intcode = Intcode::new(program);
-            intcode.set_input(s.clone());
-            intcode.compute().unwrap();
-            intcode
-        })
-        .collect::<Vec<Intcode>>();
-
-    let mut i = 0;
-    let mut current_input = 0;
-    loop {
-        let intcode = &mut intcodes[i];
-        intcode.set_input(current_input);
-        intcode.compute()?;
-
-        current_input = intcode.last_output().unwrap();
-
-        if i == phase_settings.len() - 1 && intcode.halted() {
-            break;
-        }
+fn calculate_signal_feedback(
+    program: &Program,
+    phase_settings: &[i64],
+) -> Result<i64, IntcodeError> {
+    let (mut network, names) = amplifiers(program, phase_settings);
+    let names = names.iter().map(String::as_str).collect::<Vec<&str>>();
+    network.ring(&names)?.input(names[0], 0)?.run()?;
+
+    network
+        .last_output(names[names.len() - 1])
+        .ok_or(IntcodeError::NoOutput)
+}
 
-        i = (i + 1) % phase_settings.len();
+// One amplifier per phase setting, named amp0, amp1 and so on, each already given its setting
+fn amplifiers(program: &Program, phase_settings: &[i64]) -> (Network, Vec<String>) {
+    let names = (0..phase_settings.len())
+        .map(|i| format!("amp{}", i))
+        .collect::<Vec<String>>();
+
+    let mut network = Network::new();
+    for (name, setting) in names.iter().zip(phase_settings) {
+        let mut intcode = program.machine();
+        intcode.push_input(*setting);
+        network.add(name, intcode);
     }
 
-    Ok(current_input)
+    (network, names)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use intcode::Engine;
 
     #[test]
     fn signal_1() {
-        let program = vec![
-            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
-        ];
-        let phase_settings = vec![4, 3, 2, 1, 0];
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![
+                3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+            ])
+            .engine(engine);
+            let phase_settings = vec![4, 3, 2, 1, 0];
+
+            assert_eq!(Ok(43210), calculate_signal(&program, &phase_settings));
+        }
+    }
 
-        assert_eq!(Ok(43210), calculate_signal(&program, &phase_settings));
+    #[test]
+    fn signal_through_many_amplifiers() {
+        // Each amplifier adds one to its signal
+        let program = Program::new(vec![3, 11, 3, 12, 1001, 12, 1, 12, 4, 12, 99, 0, 0]);
+
+        assert_eq!(Ok(30), calculate_signal(&program, &[0; 30]));
     }
 
     #[test]
     fn part_one_example_one_should_return_43210() {
-        let program = vec![
-            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
-        ];
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![
+                3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+            ])
+            .engine(engine);
 
-        assert_eq!(Ok(43210), part_one(&program));
+            assert_eq!(Ok(43210), part_one(&program));
+        }
     }
 
     #[test]
     fn signal_2() {
-        let program = vec![
-            3, 23, 3, 24, 1002, 24, 10, 24, 1002, 23, -1, 23, 101, 5, 23, 23, 1, 24, 23, 23, 4, 23,
-            99, 0, 0,
-        ];
-        let phase_settings = vec![0, 1, 2, 3, 4];
-
-        assert_eq!(Ok(54321), calculate_signal(&program, &phase_settings));
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![
+                3, 23, 3, 24, 1002, 24, 10, 24, 1002, 23, -1, 23, 101, 5, 23, 23, 1, 24, 23, 23, 4,
+                23, 99, 0, 0,
+            ])
+            .engine(engine);
+            let phase_settings = vec![0, 1, 2, 3, 4];
+
+            assert_eq!(Ok(54321), calculate_signal(&program, &phase_settings));
+        }
     }
 
     #[test]
     fn part_one_example_two_should_return_54321() {
-        let program = vec![
-            3, 23, 3, 24, 1002, 24, 10, 24, 1002, 23, -1, 23, 101, 5, 23, 23, 1, 24, 23, 23, 4, 23,
-            99, 0, 0,
-        ];
-
-        assert_eq!(Ok(54321), part_one(&program));
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![
+                3, 23, 3, 24, 1002, 24, 10, 24, 1002, 23, -1, 23, 101, 5, 23, 23, 1, 24, 23, 23, 4,
+                23, 99, 0, 0,
+            ])
+            .engine(engine);
+
+            assert_eq!(Ok(54321), part_one(&program));
+        }
     }
 
     #[test]
     fn signal_3() {
-        let program = vec![
-            3, 31, 3, 32, 1002, 32, 10, 32, 1001, 31, -2, 31, 1007, 31, 0, 33, 1002, 33, 7, 33, 1,
-            33, 31, 31, 1, 32, 31, 31, 4, 31, 99, 0, 0, 0,
-        ];
-        let phase_settings = vec![1, 0, 4, 3, 2];
-
-        assert_eq!(Ok(65210), calculate_signal(&program, &phase_settings));
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![
+                3, 31, 3, 32, 1002, 32, 10, 32, 1001, 31, -2, 31, 1007, 31, 0, 33, 1002, 33, 7, 33,
+                1, 33, 31, 31, 1, 32, 31, 31, 4, 31, 99, 0, 0, 0,
+            ])
+            .engine(engine);
+            let phase_settings = vec![1, 0, 4, 3, 2];
+
+            assert_eq!(Ok(65210), calculate_signal(&program, &phase_settings));
+        }
     }
 
     #[test]
     fn part_one_example_three_should_return_65210() {
-        let program = vec![
-            3, 31, 3, 32, 1002, 32, 10, 32, 1001, 31, -2, 31, 1007, 31, 0, 33, 1002, 33, 7, 33, 1,
-            33, 31, 31, 1, 32, 31, 31, 4, 31, 99, 0, 0, 0,
-        ];
-
-        assert_eq!(Ok(65210), part_one(&program));
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![
+                3, 31, 3, 32, 1002, 32, 10, 32, 1001, 31, -2, 31, 1007, 31, 0, 33, 1002, 33, 7, 33,
+                1, 33, 31, 31, 1, 32, 31, 31, 4, 31, 99, 0, 0, 0,
+            ])
+            .engine(engine);
+
+            assert_eq!(Ok(65210), part_one(&program));
+        }
     }
 
     #[test]
     fn signal_feedback_1() {
-        let program = vec![
-            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
-            28, 1005, 28, 6, 99, 0, 0, 5,
-        ];
-        let phase_settings = vec![9, 8, 7, 6, 5];
-
-        assert_eq!(
-            Ok(139629729),
-            calculate_signal_feedback(&program, &phase_settings)
-        );
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![
+                3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28,
+                -1, 28, 1005, 28, 6, 99, 0, 0, 5,
+            ])
+            .engine(engine);
+            let phase_settings = vec![9, 8, 7, 6, 5];
+
+            assert_eq!(
+                Ok(139629729),
+                calculate_signal_feedback(&program, &phase_settings)
+            );
+        }
     }
 
     #[test]
     fn signal_feedback_2() {
-        let program = vec![
-            3, 52, 1001, 52, -5, 52, 3, 53, 1, 52, 56, 54, 1007, 54, 5, 55, 1005, 55, 26, 1001, 54,
-            -5, 54, 1105, 1, 12, 1, 53, 54, 53, 1008, 54, 0, 55, 1001, 55, 1, 55, 2, 53, 55, 53, 4,
-            53, 1001, 56, -1, 56, 1005, 56, 6, 99, 0, 0, 0, 0, 10,
-        ];
-        let phase_settings = vec![9, 7, 8, 5, 6];
-
-        assert_eq!(
-            Ok(18216),
-            calculate_signal_feedback(&program, &phase_settings)
-        );
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![
+                3, 52, 1001, 52, -5, 52, 3, 53, 1, 52, 56, 54, 1007, 54, 5, 55, 1005, 55, 26, 1001,
+                54, -5, 54, 1105, 1, 12, 1, 53, 54, 53, 1008, 54, 0, 55, 1001, 55, 1, 55, 2, 53,
+                55, 53, 4, 53, 1001, 56, -1, 56, 1005, 56, 6, 99, 0, 0, 0, 0, 10,
+            ])
+            .engine(engine);
+            let phase_settings = vec![9, 7, 8, 5, 6];
+
+            assert_eq!(
+                Ok(18216),
+                calculate_signal_feedback(&program, &phase_settings)
+            );
+        }
+    }
+
+    #[test]
+    fn signal_feedback_threaded() {
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(vec![
+                3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28,
+                -1, 28, 1005, 28, 6, 99, 0, 0, 5,
+            ])
+            .engine(engine);
+            let (mut network, names) = amplifiers(&program, &[9, 8, 7, 6, 5]);
+            let names = names.iter().map(String::as_str).collect::<Vec<&str>>();
+            network.ring(&names).unwrap().input("amp0", 0).unwrap();
+
+            assert_eq!(Ok(intcode::NetworkState::Halted), network.run_threaded());
+            assert_eq!(Some(139629729), network.last_output("amp4"));
+        }
     }
 }
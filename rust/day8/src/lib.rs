@@ -1,10 +1,9 @@
 use std::error::Error;
-use std::fs;
 
-pub mod config;
+use common::{Answer, Solution};
 
 #[derive(Debug)]
-struct Layer {
+pub struct Layer {
     rows: Vec<Vec<i32>>
 }
 
@@ -24,23 +23,24 @@ impl Layer {
     }
 }
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let input = fs::read_to_string(config.filename)?;
-    let layers = parse_layers(&input, 25, 6);
+pub struct Day8;
 
-    match config.part {
-        config::Part::PartOne => {
-            println!("{}", part_one(&layers));
-        }
-        config::Part::PartTwo => {
-            let image = part_two(&layers);
-            for row in image {
-                println!("{}", row);
-            }
-        }
+impl Solution for Day8 {
+    const DAY: u8 = 8;
+
+    type Input = Vec<Layer>;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_layers(input.trim(), 25, 6))
+    }
+
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_one(input).into())
     }
 
-    Ok(())
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(part_two(input).into())
+    }
 }
 
 fn parse_layers(input: &str, width: usize, height: usize) -> Vec<Layer> {
@@ -1,34 +1,66 @@
 use std::error::Error;
-use std::fs;
 
-use intcode::Intcode;
+use common::config::Config;
+use common::{Answer, Solution};
+use intcode::Program;
 
-pub mod config;
-pub mod intcode;
+pub struct Day9;
 
-pub fn run(config: config::Config) -> Result<(), Box<dyn Error>> {
-    let program: Vec<i64> = fs::read_to_string(config.filename)?
-        .split(",")
-        .map(|s| s.trim().parse::<i64>().unwrap())
-        .collect();
+impl Solution for Day9 {
+    const DAY: u8 = 9;
 
-    match config.part {
-        config::Part::PartOne => {
-            let mut intcode = Intcode::new(&program);
-            intcode.set_input(1);
-            intcode.compute()?;
-            println!("{:?}", intcode.outputs());
-        }
-        config::Part::PartTwo => {
-            let mut intcode = Intcode::new(&program);
-            intcode.set_input(2);
-            intcode.compute()?;
-            println!("{:?}", intcode.outputs());
-        }
+    type Input = Program;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(Program::new(intcode::parse_program(input)?))
+    }
+
+    fn parse_with(input: &str, config: &Config) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(Self::parse(input)?.settings(|name| config.option(name))?)
+    }
+
+    fn part_one(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(boost(input, 1)?.into())
+    }
+
+    fn part_two(input: &Self::Input) -> Result<Answer, Box<dyn Error>> {
+        Ok(boost(input, 2)?.into())
     }
+}
+
+fn boost(program: &Program, input: i64) -> Result<i64, Box<dyn Error>> {
+    let mut intcode = program.machine();
+    intcode.push_input(input);
+    intcode.run_to_halt()?;
 
-    Ok(())
+    // BOOST outputs the opcodes it finds malfunctioning before the keycode
+    match intcode.drain_outputs().as_slice() {
+        [keycode] => Ok(*keycode),
+        outputs => Err(format!("BOOST reported malfunctioning opcodes: {:?}", outputs).into()),
+    }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use intcode::Engine;
+
+    #[test]
+    fn boost_should_output_large_numbers() {
+        let code = intcode::assemble(
+            "
+            MUL #34915192, #34915192 -> [result]
+            OUT [result]
+            HALT
+            result: DATA 0
+            ",
+        )
+        .unwrap();
+
+        for &engine in Engine::ALL.iter() {
+            let program = Program::new(code.clone()).engine(engine);
+
+            assert_eq!(1219070632396864, boost(&program, 1).unwrap());
+        }
+    }
+}
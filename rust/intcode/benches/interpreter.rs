@@ -0,0 +1,60 @@
+use std::fs;
+use std::time::{Duration, Instant};
+
+use intcode::{Engine, Intcode};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../inputs/day9.txt");
+
+// Part 2 of day 9 runs a few hundred thousand instructions, which is enough to
+// compare the interpreter with and without its decode cache, and the compiled engine
+fn main() {
+    let program = match fs::read_to_string(INPUT) {
+        Ok(contents) => intcode::parse_program(&contents).expect("Invalid day 9 program"),
+        Err(e) => {
+            eprintln!("Skipping benchmark, failed to read {}: {}", INPUT, e);
+            return;
+        }
+    };
+
+    for &input in &[1, 2] {
+        let setups = [
+            ("decode cache off", Engine::Interpreter, false),
+            ("decode cache on", Engine::Interpreter, true),
+            ("compiled", Engine::Compiled, true),
+        ];
+
+        for &(name, engine, cached) in &setups {
+            let (steps, elapsed) = bench(&program, input, engine, cached);
+            let per_second = steps as f64 / elapsed.as_secs_f64();
+
+            println!(
+                "day9 input {} {:<16}: {:>9} steps in {:>10.3?} ({:.1} M steps/s)",
+                input,
+                name,
+                steps,
+                elapsed,
+                per_second / 1e6
+            );
+        }
+    }
+}
+
+// Best of several runs, to keep noise from other processes out of the comparison
+fn bench(program: &[i64], input: i64, engine: Engine, cached: bool) -> (u64, Duration) {
+    let mut best = Duration::from_secs(u64::MAX);
+    let mut steps = 0;
+
+    for _ in 0..10 {
+        let mut intcode = Intcode::new(program);
+        intcode.set_engine(engine);
+        intcode.set_decode_cache(cached);
+        intcode.push_input(input);
+
+        let start = Instant::now();
+        intcode.run_to_halt().expect("Day 9 program failed");
+        best = best.min(start.elapsed());
+        steps = intcode.steps();
+    }
+
+    (steps, best)
+}
@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+use intcode::{Intcode, Network, NetworkState};
+
+const NODES: usize = 32;
+
+// Passes a token around, spinning for a while before forwarding it; the node that gets it for
+// the tenth time halts. Each node also starts a token of its own so every node has work to do.
+const NODE: &str = "
+    loop:  IN -> [token]
+           ADD #0, #0 -> [i]
+    spin:  ADD [i], #1 -> [i]
+           LT [i], #20000 -> [more]
+           JNZ [more], #spin
+           ADD [token], #1 -> [token]
+           OUT [token]
+           LT [token], #10 -> [more]
+           JNZ [more], #loop
+           HALT
+    token: DATA 0
+    i:     DATA 0
+    more:  DATA 0
+";
+
+fn main() {
+    let program = intcode::assemble(NODE).expect("Invalid node program");
+
+    for &threaded in &[false, true] {
+        let (state, elapsed) = bench(&program, threaded);
+
+        println!(
+            "{}-node ring, {:<11}: {:?} in {:>10.3?}",
+            NODES,
+            if threaded { "threaded" } else { "cooperative" },
+            state,
+            elapsed
+        );
+    }
+}
+
+fn bench(program: &[i64], threaded: bool) -> (NetworkState, Duration) {
+    let names = (0..NODES)
+        .map(|i| format!("nic{}", i))
+        .collect::<Vec<String>>();
+    let names = names.iter().map(|n| n.as_str()).collect::<Vec<&str>>();
+
+    let mut network = Network::new();
+    for name in &names {
+        network.add(name, Intcode::new(program));
+        network.input(name, 0).unwrap();
+    }
+    network.ring(&names).unwrap();
+
+    let start = Instant::now();
+    let state = if threaded {
+        network.run_threaded()
+    } else {
+        network.run()
+    };
+
+    (state.expect("Network failed"), start.elapsed())
+}
@@ -0,0 +1,632 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::disasm::{decode, Line};
+use crate::operation::{Opcode, ParameterMode};
+
+// Why control goes from one block to another
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Edge {
+    Next,
+    Jump,
+    // From a call to the instruction after it, as the callee is expected to come back there
+    Return,
+}
+
+impl Edge {
+    fn label(self) -> &'static str {
+        match self {
+            Edge::Next => "next",
+            Edge::Jump => "jump",
+            Edge::Return => "return",
+        }
+    }
+}
+
+// Instructions that always run one after the other, entered only at `start`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Block {
+    pub start: usize,
+    pub end: usize,
+    pub lines: Vec<Line>,
+    pub successors: Vec<(usize, Edge)>,
+    // Ends in a jump whose target is only known at run time
+    pub indirect: bool,
+}
+
+// What can be told about a program without running it, found by following every path from
+// address 0.
+//
+// Jump targets read from memory are followed when no instruction writes to that cell; the rest
+// are reported as indirect. Calls are recognised the same way the profiler does, by a constant
+// return address stored right before a jump, and the address they return to is followed too.
+// Writes through the relative base could land anywhere and are only counted.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    size: usize,
+    instructions: BTreeMap<usize, Line>,
+    blocks: Vec<Block>,
+    code: Vec<bool>,
+    writes: BTreeMap<usize, BTreeSet<usize>>,
+    relative_writes: Vec<usize>,
+    indirect_jumps: Vec<usize>,
+    invalid: BTreeSet<usize>,
+}
+
+struct Successor {
+    address: usize,
+    edge: Edge,
+    previous: Option<usize>,
+}
+
+pub fn analyze(program: &[i64]) -> Analysis {
+    // Resolving a jump depends on which cells are written, which depends on what's reachable, so
+    // passes are repeated until the set of written cells stops growing
+    let mut written = BTreeSet::new();
+    loop {
+        let analysis = traverse(program, &written);
+        let before = written.len();
+        written.extend(analysis.writes.keys().copied());
+
+        if written.len() == before {
+            return analysis;
+        }
+    }
+}
+
+fn traverse(program: &[i64], written: &BTreeSet<usize>) -> Analysis {
+    let mut analysis = Analysis {
+        size: program.len(),
+        code: vec![false; program.len()],
+        ..Analysis::default()
+    };
+    let mut leaders = BTreeSet::new();
+    let mut edges = BTreeMap::new();
+    let mut pending = vec![Successor {
+        address: 0,
+        edge: Edge::Next,
+        previous: None,
+    }];
+    leaders.insert(0);
+
+    while let Some(Successor {
+        address, previous, ..
+    }) = pending.pop()
+    {
+        if analysis.instructions.contains_key(&address) || analysis.invalid.contains(&address) {
+            continue;
+        }
+
+        let line = match decode(program, address) {
+            Some(line) => line,
+            None => {
+                analysis.invalid.insert(address);
+                continue;
+            }
+        };
+
+        let (opcode, parameters) = match &line {
+            Line::Instruction {
+                opcode, parameters, ..
+            } => (*opcode, parameters.clone()),
+            Line::Data { .. } => unreachable!("Decoded data"),
+        };
+        let end = address + line.size();
+        for cell in &mut analysis.code[address..end] {
+            *cell = true;
+        }
+
+        if let Some(result) = opcode.result_parameter().map(|i| parameters[i]) {
+            match result.mode {
+                ParameterMode::Position if result.value >= 0 => {
+                    analysis
+                        .writes
+                        .entry(result.value as usize)
+                        .or_default()
+                        .insert(address);
+                }
+                ParameterMode::Relative => analysis.relative_writes.push(address),
+                _ => {}
+            }
+        }
+
+        let (successors, indirect) =
+            successors(program, written, &analysis.instructions, &line, previous);
+        if indirect {
+            analysis.indirect_jumps.push(address);
+        }
+
+        for successor in &successors {
+            if successor.edge != Edge::Next {
+                leaders.insert(successor.address);
+            }
+        }
+        if opcode == Opcode::JumpIfTrue || opcode == Opcode::JumpIfFalse {
+            leaders.insert(end);
+        }
+
+        edges.insert(
+            address,
+            successors
+                .iter()
+                .map(|s| (s.address, s.edge))
+                .collect::<Vec<(usize, Edge)>>(),
+        );
+        analysis.instructions.insert(address, line);
+        pending.extend(successors.into_iter().rev());
+    }
+
+    analysis.relative_writes.sort_unstable();
+    analysis.indirect_jumps.sort_unstable();
+    analysis.blocks = blocks(
+        &analysis.instructions,
+        &analysis.indirect_jumps,
+        &leaders,
+        &edges,
+    );
+    analysis
+}
+
+// Where control can go after `line`, and whether it's a jump to an unknown address
+fn successors(
+    program: &[i64],
+    written: &BTreeSet<usize>,
+    instructions: &BTreeMap<usize, Line>,
+    line: &Line,
+    previous: Option<usize>,
+) -> (Vec<Successor>, bool) {
+    let (address, opcode, parameters) = match line {
+        Line::Instruction {
+            address,
+            opcode,
+            parameters,
+        } => (*address, *opcode, parameters),
+        Line::Data { .. } => return (Vec::new(), false),
+    };
+    let end = address + line.size();
+    let next = |edge| Successor {
+        address: end,
+        edge,
+        previous: Some(address),
+    };
+
+    let condition = match opcode {
+        Opcode::Halt => return (Vec::new(), false),
+        Opcode::JumpIfTrue | Opcode::JumpIfFalse => parameters[0],
+        _ => return (vec![next(Edge::Next)], false),
+    };
+
+    // A constant condition means the jump is always or never taken
+    let taken = match condition.mode {
+        ParameterMode::Immediate => Some((condition.value != 0) == (opcode == Opcode::JumpIfTrue)),
+        _ => None,
+    };
+    if taken == Some(false) {
+        return (vec![next(Edge::Next)], false);
+    }
+
+    let mut successors = Vec::new();
+    if taken.is_none() {
+        successors.push(next(Edge::Next));
+    }
+
+    let target = parameters[1];
+    let target = match target.mode {
+        ParameterMode::Immediate => Some(target.value),
+        ParameterMode::Position
+            if target.value >= 0 && !written.contains(&(target.value as usize)) =>
+        {
+            Some(program.get(target.value as usize).copied().unwrap_or(0))
+        }
+        _ => None,
+    };
+
+    match target {
+        // Negative targets fail at run time, so there is nothing to follow
+        Some(target) if target < 0 => {}
+        Some(target) => successors.push(Successor {
+            address: target as usize,
+            edge: Edge::Jump,
+            previous: None,
+        }),
+        None => return (successors, true),
+    }
+
+    let stores_return_address = previous
+        .and_then(|previous| instructions.get(&previous))
+        .and_then(constant_result)
+        == Some(end as i64);
+    if taken == Some(true) && stores_return_address {
+        successors.push(next(Edge::Return));
+    }
+
+    (successors, false)
+}
+
+// The value an instruction stores when it only depends on immediate parameters. One that would
+// overflow fails at run time, so it isn't constant either.
+fn constant_result(line: &Line) -> Option<i64> {
+    let (opcode, parameters) = match line {
+        Line::Instruction {
+            opcode, parameters, ..
+        } => (opcode, parameters),
+        Line::Data { .. } => return None,
+    };
+
+    let values = parameters
+        .iter()
+        .take(2)
+        .map(|p| Some(p.value).filter(|_| p.mode == ParameterMode::Immediate))
+        .collect::<Option<Vec<i64>>>()?;
+
+    match opcode {
+        Opcode::Add => values[0].checked_add(values[1]),
+        Opcode::Multiply => values[0].checked_mul(values[1]),
+        Opcode::LessThan => Some((values[0] < values[1]) as i64),
+        Opcode::Equals => Some((values[0] == values[1]) as i64),
+        _ => None,
+    }
+}
+
+fn blocks(
+    instructions: &BTreeMap<usize, Line>,
+    indirect_jumps: &[usize],
+    leaders: &BTreeSet<usize>,
+    edges: &BTreeMap<usize, Vec<(usize, Edge)>>,
+) -> Vec<Block> {
+    // Anything reached by falling through from a different block starts one too, as happens
+    // when two instructions overlap
+    let starts = instructions
+        .keys()
+        .filter(|address| leaders.contains(address))
+        .copied()
+        .collect::<Vec<usize>>();
+
+    starts
+        .iter()
+        .map(|&start| {
+            let mut lines = Vec::new();
+            let mut address = start;
+
+            loop {
+                let line = instructions[&address].clone();
+                let next = address + line.size();
+                let successors = &edges[&address];
+                lines.push(line);
+
+                let falls_through = successors.len() == 1
+                    && successors[0] == (next, Edge::Next)
+                    && instructions.contains_key(&next)
+                    && !leaders.contains(&next);
+                if !falls_through {
+                    return Block {
+                        start,
+                        end: next,
+                        lines,
+                        successors: successors.clone(),
+                        indirect: indirect_jumps.contains(&address),
+                    };
+                }
+
+                address = next;
+            }
+        })
+        .collect()
+}
+
+impl Analysis {
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn instruction(&self, address: usize) -> Option<&Line> {
+        self.instructions.get(&address)
+    }
+
+    // Whether a reachable instruction covers the cell
+    pub fn is_code(&self, address: usize) -> bool {
+        self.code.get(address).copied().unwrap_or(false)
+    }
+
+    // Runs of cells no reachable instruction covers, as `start..end`
+    pub fn data(&self) -> Vec<(usize, usize)> {
+        let mut data = Vec::new();
+        let mut start = None;
+
+        for address in 0..=self.size {
+            match (start, self.is_code(address) || address == self.size) {
+                (None, false) => start = Some(address),
+                (Some(s), true) => {
+                    data.push((s, address));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+
+        data
+    }
+
+    // Cells written through position parameters, each with the instructions writing them
+    pub fn writes(&self) -> impl Iterator<Item = (usize, &BTreeSet<usize>)> {
+        self.writes
+            .iter()
+            .map(|(address, writers)| (*address, writers))
+    }
+
+    // Written cells that hold reachable code, so the program modifies itself. That includes
+    // reachable cells that only become a valid instruction once written.
+    pub fn self_modified(&self) -> Vec<usize> {
+        self.writes
+            .keys()
+            .filter(|address| self.is_code(**address) || self.invalid.contains(address))
+            .copied()
+            .collect()
+    }
+
+    pub fn relative_writes(&self) -> &[usize] {
+        &self.relative_writes
+    }
+
+    pub fn inputs(&self) -> Vec<usize> {
+        self.sites(Opcode::Set)
+    }
+
+    pub fn outputs(&self) -> Vec<usize> {
+        self.sites(Opcode::Output)
+    }
+
+    pub fn indirect_jumps(&self) -> &[usize] {
+        &self.indirect_jumps
+    }
+
+    // Reachable addresses that don't hold a valid instruction, which fail if ever executed
+    pub fn invalid(&self) -> Vec<usize> {
+        self.invalid.iter().copied().collect()
+    }
+
+    fn sites(&self, opcode: Opcode) -> Vec<usize> {
+        self.instructions
+            .iter()
+            .filter(|(_, line)| match line {
+                Line::Instruction { opcode: o, .. } => *o == opcode,
+                Line::Data { .. } => false,
+            })
+            .map(|(address, _)| *address)
+            .collect()
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        let addresses = |addresses: &[usize]| match addresses {
+            [] => String::from("none"),
+            _ => addresses
+                .iter()
+                .map(|a| format!("{:04}", a))
+                .collect::<Vec<String>>()
+                .join(", "),
+        };
+
+        writeln!(
+            summary,
+            "Reachable: {} instructions in {} blocks, {} of {} cells",
+            self.instructions.len(),
+            self.blocks.len(),
+            self.code.iter().filter(|c| **c).count(),
+            self.size
+        )
+        .unwrap();
+        writeln!(summary, "Inputs: {}", addresses(&self.inputs())).unwrap();
+        writeln!(summary, "Outputs: {}", addresses(&self.outputs())).unwrap();
+        writeln!(
+            summary,
+            "Indirect jumps: {}",
+            addresses(&self.indirect_jumps)
+        )
+        .unwrap();
+        writeln!(summary, "Invalid: {}", addresses(&self.invalid())).unwrap();
+        writeln!(
+            summary,
+            "Self-modified: {}",
+            addresses(&self.self_modified())
+        )
+        .unwrap();
+        writeln!(
+            summary,
+            "Written: {} cells, and {} instructions write through the relative base",
+            self.writes.len(),
+            self.relative_writes.len()
+        )
+        .unwrap();
+
+        let data = self
+            .data()
+            .iter()
+            .map(|(start, end)| format!("{:04}..{:04}", start, end))
+            .collect::<Vec<String>>();
+        writeln!(
+            summary,
+            "Data: {}",
+            if data.is_empty() {
+                String::from("none")
+            } else {
+                data.join(", ")
+            }
+        )
+        .unwrap();
+
+        for block in &self.blocks {
+            let successors = block
+                .successors
+                .iter()
+                .map(|(address, edge)| format!("{:04} ({})", address, edge.label()))
+                .chain(Some(String::from("?")).filter(|_| block.indirect))
+                .collect::<Vec<String>>();
+
+            writeln!(
+                summary,
+                "\n{:04}..{:04} -> {}",
+                block.start,
+                block.end,
+                if successors.is_empty() {
+                    String::from("exit")
+                } else {
+                    successors.join(", ")
+                }
+            )
+            .unwrap();
+
+            for line in &block.lines {
+                writeln!(summary, "    {}", line).unwrap();
+            }
+        }
+
+        summary
+    }
+
+    // The control-flow graph in Graphviz format, one node per block
+    pub fn dot(&self) -> String {
+        let mut dot = String::new();
+
+        writeln!(dot, "digraph cfg {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        for block in &self.blocks {
+            let label = block
+                .lines
+                .iter()
+                .map(|line| format!("{}\\l", line))
+                .collect::<String>();
+            writeln!(dot, "    b{:04} [label=\"{}\"];", block.start, label).unwrap();
+        }
+
+        if self.blocks.iter().any(|b| b.indirect) {
+            writeln!(dot, "    indirect [shape=ellipse, label=\"?\"];").unwrap();
+        }
+
+        if !self.invalid.is_empty() {
+            writeln!(dot, "    invalid [shape=ellipse, label=\"invalid\"];").unwrap();
+        }
+
+        for block in &self.blocks {
+            for (address, edge) in &block.successors {
+                let target = if self.invalid.contains(address) {
+                    String::from("invalid")
+                } else {
+                    format!("b{:04}", address)
+                };
+                let style = match edge {
+                    Edge::Next => "",
+                    Edge::Jump => " [label=\"jump\"]",
+                    Edge::Return => " [label=\"return\", style=dashed]",
+                };
+
+                writeln!(dot, "    b{:04} -> {}{};", block.start, target, style).unwrap();
+            }
+
+            if block.indirect {
+                writeln!(dot, "    b{:04} -> indirect [style=dotted];", block.start).unwrap();
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branches_split_blocks() {
+        // Outputs 0 when the input is 0, and 1 otherwise
+        let program = vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+        let analysis = analyze(&program);
+
+        assert_eq!(
+            vec![0, 5, 9],
+            analysis
+                .blocks()
+                .iter()
+                .map(|b| b.start)
+                .collect::<Vec<usize>>()
+        );
+        assert_eq!(
+            vec![(5, Edge::Next), (9, Edge::Jump)],
+            analysis.blocks()[0].successors
+        );
+        assert_eq!(vec![(9, Edge::Next)], analysis.blocks()[1].successors);
+        assert_eq!(vec![0], analysis.inputs());
+        assert_eq!(vec![9], analysis.outputs());
+        assert_eq!(vec![(12, 16)], analysis.data());
+    }
+
+    #[test]
+    fn calls_return_to_the_next_instruction() {
+        let source = "
+                    ARB #100
+                    ADD #back, #0 -> [rb+0]
+                    JNZ #1, #double
+            back:   OUT [rb+1]
+                    HALT
+            double: MUL #2, #21 -> [rb+1]
+                    JZ #0, [rb+0]
+        ";
+        let program = crate::asm::assemble(source).unwrap();
+        let analysis = analyze(&program);
+
+        assert_eq!(
+            vec![(12, Edge::Jump), (9, Edge::Return)],
+            analysis.blocks()[0].successors
+        );
+        assert!(analysis.blocks().iter().any(|b| b.start == 9));
+        assert_eq!(&[16], analysis.indirect_jumps());
+        assert_eq!(&[2, 12], analysis.relative_writes());
+        assert!(analysis.dot().contains("b0012 -> indirect [style=dotted];"));
+    }
+
+    #[test]
+    fn self_modification() {
+        // Replaces the second OUT with a HALT before reaching it
+        let program = vec![1101, 0, 99, 6, 104, 7, 104, 8, 99];
+        let analysis = analyze(&program);
+
+        assert_eq!(vec![6], analysis.self_modified());
+        assert_eq!(vec![4, 6], analysis.outputs());
+        assert!(analysis.data().is_empty());
+        // Day 5 starts by turning the opcode after its input into a valid one
+        let analysis = analyze(&[3, 11, 1, 11, 6, 6, 1100, 1, 1, 11, 99, 0]);
+        assert_eq!(vec![6], analysis.self_modified());
+        assert_eq!(vec![6], analysis.invalid());
+    }
+
+    #[test]
+    fn written_jump_targets_are_indirect() {
+        assert!(analyze(&[1105, 1, 3, 99]).indirect_jumps().is_empty());
+
+        let analysis = analyze(&[1101, 0, 9, 10, 5, 1, 10, 99, 99, 99, 8]);
+        assert_eq!(&[4], analysis.indirect_jumps());
+        assert!(analysis.blocks()[0].indirect);
+        assert_eq!(vec![(7, Edge::Next)], analysis.blocks()[0].successors);
+    }
+
+    #[test]
+    fn overflowing_constants_are_not_constant() {
+        let analysis = analyze(&[1101, i64::MAX, 1, 100, 1105, 1, 7, 99]);
+
+        assert!(analysis.invalid().is_empty());
+        assert_eq!(vec![(7, Edge::Jump)], analysis.blocks()[0].successors);
+    }
+
+    #[test]
+    fn invalid_instructions_are_reported() {
+        let analysis = analyze(&[1105, 1, 4, 99, 98]);
+
+        assert_eq!(vec![4], analysis.invalid());
+        assert!(analysis.self_modified().is_empty());
+        assert!(analysis
+            .dot()
+            .contains("b0000 -> invalid [label=\"jump\"];"));
+    }
+}
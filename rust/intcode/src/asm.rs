@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use crate::operation::{Opcode, ParameterMode};
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Number(i64),
+    Label(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct Operand {
+    mode: ParameterMode,
+    value: Value,
+}
+
+#[derive(Debug, PartialEq)]
+enum Statement {
+    Instruction {
+        opcode: Opcode,
+        operands: Vec<Operand>,
+    },
+    Data(Vec<Value>),
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction { operands, .. } => operands.len() + 1,
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+// Accepts the disassembler's listings as well as hand written source:
+//
+//     ; Doubles its input forever
+//     loop: IN -> [value]
+//           MUL [value], #2 -> [value]
+//           OUT [value]
+//           JNZ #1, #loop
+//     value: DATA 0
+//
+// Operands are `[n]` or `n` for position, `#n` for immediate and `[rb+n]` or `rb+n` for
+// relative mode; labels can stand in for any number.
+pub fn assemble(source: &str) -> Result<Vec<i64>, String> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let number = i + 1;
+        let mut line = line.split(';').next().unwrap_or("").trim();
+
+        // Leading `name:` labels and the `0042:` addresses found in listings
+        while let Some(colon) = line.find(':') {
+            let prefix = line[..colon].trim();
+
+            if is_label(prefix) {
+                if labels.insert(prefix, address).is_some() {
+                    return Err(format!("Line {}: Duplicate label {}", number, prefix));
+                }
+            } else if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_digit()) {
+                return Err(format!("Line {}: Invalid label {}", number, prefix));
+            }
+
+            line = line[colon + 1..].trim();
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(line).map_err(|e| format!("Line {}: {}", number, e))?;
+        address += statement.size();
+        statements.push((number, statement));
+    }
+
+    let mut program = Vec::with_capacity(address);
+    for (number, statement) in statements {
+        let resolve = |value: &Value| match value {
+            Value::Number(n) => Ok(*n),
+            Value::Label(label) => match labels.get(label.as_str()) {
+                Some(address) => Ok(*address as i64),
+                None => Err(format!("Line {}: Unknown label {}", number, label)),
+            },
+        };
+
+        match statement {
+            Statement::Instruction { opcode, operands } => {
+                let modes = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, o)| o.mode.code() * 10_i64.pow(i as u32 + 2))
+                    .sum::<i64>();
+                program.push(opcode.code() + modes);
+
+                for operand in operands {
+                    program.push(resolve(&operand.value)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in values {
+                    program.push(resolve(&value)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+fn parse_statement(line: &str) -> Result<Statement, String> {
+    let (mnemonic, rest) = match line.find(char::is_whitespace) {
+        Some(i) => (&line[..i], line[i..].trim()),
+        None => (line, ""),
+    };
+
+    // `->` only marks the result operand, so it's just another separator
+    let operands = rest
+        .split("->")
+        .flat_map(|part| part.split(','))
+        .map(|o| String::from(o.trim()))
+        .filter(|o| !o.is_empty())
+        .collect::<Vec<String>>();
+
+    if mnemonic.to_uppercase() == "DATA" {
+        let values = operands
+            .iter()
+            .map(|o| parse_value(o))
+            .collect::<Result<Vec<Value>, String>>()?;
+
+        return Ok(Statement::Data(values));
+    }
+
+    let opcode = match Opcode::from_mnemonic(mnemonic) {
+        Some(opcode) => opcode,
+        None => return Err(format!("Unknown mnemonic {}", mnemonic)),
+    };
+
+    if operands.len() != opcode.parameter_count() {
+        return Err(format!(
+            "{} takes {} operand(s), got {}",
+            opcode.mnemonic(),
+            opcode.parameter_count(),
+            operands.len()
+        ));
+    }
+
+    let operands = operands
+        .iter()
+        .map(|o| parse_operand(o))
+        .collect::<Result<Vec<Operand>, String>>()?;
+
+    if let Some(i) = opcode.result_parameter() {
+        if operands[i].mode == ParameterMode::Immediate {
+            return Err(format!("{} can't write to an immediate", opcode.mnemonic()));
+        }
+    }
+
+    Ok(Statement::Instruction { opcode, operands })
+}
+
+fn parse_operand(operand: &str) -> Result<Operand, String> {
+    if let Some(value) = operand.strip_prefix('#') {
+        return Ok(Operand {
+            mode: ParameterMode::Immediate,
+            value: parse_value(value.trim())?,
+        });
+    }
+
+    let inner = match operand.strip_prefix('[') {
+        Some(rest) => match rest.strip_suffix(']') {
+            Some(inner) => inner.trim(),
+            None => return Err(format!("Unclosed bracket in {}", operand)),
+        },
+        None => operand,
+    };
+
+    match inner.strip_prefix("rb") {
+        Some(offset) if !is_label(inner) => {
+            let offset = offset.trim();
+            let value = match offset.strip_prefix('+') {
+                Some(rest) => parse_value(rest.trim())?,
+                None if offset.is_empty() => Value::Number(0),
+                None => parse_value(offset)?,
+            };
+
+            Ok(Operand {
+                mode: ParameterMode::Relative,
+                value,
+            })
+        }
+        _ => Ok(Operand {
+            mode: ParameterMode::Position,
+            value: parse_value(inner)?,
+        }),
+    }
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Ok(number) = value.parse::<i64>() {
+        return Ok(Value::Number(number));
+    }
+
+    if is_label(value) {
+        return Ok(Value::Label(String::from(value)));
+    }
+
+    Err(format!("Invalid operand {}", value))
+}
+
+fn is_label(value: &str) -> bool {
+    let mut chars = value.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            value != "rb" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::listing;
+
+    #[test]
+    fn assemble_with_labels() {
+        let source = "
+            ; Doubles its input forever
+            loop: IN -> [value]
+                  MUL [value], #2 -> [value]
+                  OUT [value]
+                  JNZ #1, #loop
+            value: DATA 0
+        ";
+
+        assert_eq!(
+            Ok(vec![3, 11, 1002, 11, 2, 11, 4, 11, 1105, 1, 0, 0]),
+            assemble(source)
+        );
+    }
+
+    #[test]
+    fn assemble_relative_operands() {
+        let source = "ARB #-1\nOUT [rb-3]\nADD rb+1, #2 -> [rb]\nHALT";
+
+        assert_eq!(
+            Ok(vec![109, -1, 204, -3, 21201, 1, 2, 0, 99]),
+            assemble(source)
+        );
+    }
+
+    #[test]
+    fn assemble_large_number_program() {
+        let source = "
+            MUL #34915192, #34915192 -> [result]
+            OUT [result]
+            HALT
+            result: DATA 0
+        ";
+
+        assert_eq!(
+            Ok(vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0]),
+            assemble(source)
+        );
+    }
+
+    #[test]
+    fn listing_round_trips() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+
+        assert_eq!(Ok(program.clone()), assemble(&listing(&program)));
+    }
+
+    #[test]
+    fn unknown_label_should_fail() {
+        assert_eq!(
+            Err(String::from("Line 1: Unknown label nowhere")),
+            assemble("JZ #0, #nowhere")
+        );
+    }
+
+    #[test]
+    fn immediate_result_should_fail() {
+        assert!(assemble("ADD #1, #2 -> #3").is_err());
+    }
+
+    #[test]
+    fn wrong_operand_count_should_fail() {
+        assert_eq!(
+            Err(String::from("Line 2: OUT takes 1 operand(s), got 2")),
+            assemble("HALT\nOUT #1, #2")
+        );
+    }
+}
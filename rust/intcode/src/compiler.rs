@@ -0,0 +1,158 @@
+use crate::operation::{parse_operation, Opcode, ParameterMode};
+
+// How a machine executes its program. Both give the same results; `Compiled` is faster on long
+// running programs and quietly hands over to `Interpreter` for programs that rewrite their code.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Engine {
+    #[default]
+    Interpreter,
+    Compiled,
+}
+
+impl Engine {
+    pub const ALL: [Engine; 2] = [Engine::Interpreter, Engine::Compiled];
+
+    pub fn new(raw_value: &str) -> Result<Self, String> {
+        match raw_value.to_lowercase().as_str() {
+            "interpreter" => Ok(Engine::Interpreter),
+            "compiled" => Ok(Engine::Compiled),
+            _ => Err(format!("Invalid engine: {}", raw_value)),
+        }
+    }
+}
+
+// A parameter with its mode already applied, so running it needs no decoding
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Operand {
+    Immediate(i64),
+    Position(usize),
+    Relative(i64),
+}
+
+// Result operands are never `Immediate`, programs that try are left to the interpreter
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Op {
+    Add(Operand, Operand, Operand),
+    Multiply(Operand, Operand, Operand),
+    Input(Operand),
+    Output(Operand),
+    JumpIfTrue(Operand, Operand),
+    JumpIfFalse(Operand, Operand),
+    LessThan(Operand, Operand, Operand),
+    Equals(Operand, Operand, Operand),
+    AdjustRelativeBase(Operand),
+    Halt,
+}
+
+// Instructions are compiled the first time execution reaches them. Every cell an instruction
+// was compiled from is remembered, so a write to one of them means the program modifies itself.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Compiled {
+    ops: Vec<Option<Op>>,
+    code: Vec<bool>,
+    self_modified: bool,
+}
+
+impl Compiled {
+    pub fn get(&self, ip: usize) -> Option<Op> {
+        self.ops.get(ip).copied().flatten()
+    }
+
+    // Compiles the instruction at `ip`, or returns `None` if only the interpreter can run it:
+    // invalid instructions and negative addresses have to be reported as errors
+    pub fn compile<F: Fn(usize) -> i64>(&mut self, ip: usize, read: F) -> Option<Op> {
+        let (opcode, first_mode, second_mode, third_mode) = parse_operation(ip, read(ip)).ok()?;
+        let modes = [first_mode, second_mode, third_mode];
+        let operand = |i: usize| -> Option<Operand> {
+            let value = read(ip + i + 1);
+            match modes[i] {
+                ParameterMode::Immediate => Some(Operand::Immediate(value)),
+                ParameterMode::Position if value >= 0 => Some(Operand::Position(value as usize)),
+                ParameterMode::Position => None,
+                ParameterMode::Relative => Some(Operand::Relative(value)),
+            }
+        };
+        let result = |i: usize| operand(i).filter(|o| !matches!(o, Operand::Immediate(_)));
+
+        let op = match opcode {
+            Opcode::Add => Op::Add(operand(0)?, operand(1)?, result(2)?),
+            Opcode::Multiply => Op::Multiply(operand(0)?, operand(1)?, result(2)?),
+            Opcode::Set => Op::Input(result(0)?),
+            Opcode::Output => Op::Output(operand(0)?),
+            Opcode::JumpIfTrue => Op::JumpIfTrue(operand(0)?, operand(1)?),
+            Opcode::JumpIfFalse => Op::JumpIfFalse(operand(0)?, operand(1)?),
+            Opcode::LessThan => Op::LessThan(operand(0)?, operand(1)?, result(2)?),
+            Opcode::Equals => Op::Equals(operand(0)?, operand(1)?, result(2)?),
+            Opcode::SetRelativeBase => Op::AdjustRelativeBase(operand(0)?),
+            Opcode::Halt => Op::Halt,
+        };
+
+        let end = ip + opcode.parameter_count() + 1;
+        if self.ops.len() < end {
+            self.ops.resize(end, None);
+            self.code.resize(end, false);
+        }
+
+        self.ops[ip] = Some(op);
+        for cell in &mut self.code[ip..end] {
+            *cell = true;
+        }
+
+        Some(op)
+    }
+
+    pub fn is_code(&self, address: usize) -> bool {
+        self.code.get(address).copied().unwrap_or(false)
+    }
+
+    pub fn self_modified(&self) -> bool {
+        self.self_modified
+    }
+
+    pub fn mark_self_modified(&mut self) {
+        self.self_modified = true;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_binds_operands() {
+        let program = [21101, 4, -2, 7, 99];
+        let mut compiled = Compiled::default();
+
+        assert_eq!(
+            Some(Op::Add(
+                Operand::Immediate(4),
+                Operand::Immediate(-2),
+                Operand::Relative(7)
+            )),
+            compiled.compile(0, |a| program.get(a).copied().unwrap_or(0))
+        );
+        assert!(compiled.is_code(3));
+        assert!(!compiled.is_code(4));
+        assert_eq!(
+            Some(Op::Add(
+                Operand::Immediate(4),
+                Operand::Immediate(-2),
+                Operand::Relative(7)
+            )),
+            compiled.get(0)
+        );
+    }
+
+    #[test]
+    fn compile_leaves_errors_to_the_interpreter() {
+        let mut compiled = Compiled::default();
+
+        assert_eq!(None, compiled.compile(0, |_| 98));
+        assert_eq!(None, compiled.compile(0, |a| [11101, 1, 1, 1][a]));
+        assert_eq!(None, compiled.compile(0, |a| [4, -1][a]));
+    }
+}
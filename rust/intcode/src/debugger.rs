@@ -0,0 +1,634 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+
+use crate::disasm::{decode, Line};
+use crate::journal::Journal;
+use crate::machine::{Intcode, RunState};
+use crate::operation::{parse_opcode, Opcode};
+
+pub const HELP: &str = "\
+Commands:
+    s, step [N]               Execute N instructions (default: 1)
+    c, continue               Run until a breakpoint or watchpoint, missing input or halt
+    rs, rstep [N]             Undo N instructions (default: 1)
+    rewind <ADDRESS>          Go back to just before the last write to ADDRESS
+    goto <N>                  Go back or forward to when N instructions had executed
+    b, break <IP|MNEMONIC>    Stop before the instruction at IP, or before any instruction of a kind
+    w, watch <ADDRESS>        Stop after the value at ADDRESS changes
+    clear                     Remove every breakpoint and watchpoint
+    p, print mem[A]           Print the value at address A
+    p, print mem[A..B]        Print the values from address A up to B, at most 1000 of them
+    p, print rb|ip|steps      Print the relative base, the instruction pointer or the step count
+    set mem[A] = V            Store V at address A, which clears the history rstep and goto use
+    i, input V...             Queue input values
+    l, list [N]               Disassemble N instructions from the instruction pointer (default: 5)
+    h, help                   Print this message
+    q, quit                   Leave the debugger";
+
+const PROMPT: &str = "(intcode) ";
+
+// Longest range `print` shows, which is already more than fits on a screen
+const MAX_RANGE: usize = 1000;
+
+pub struct Debugger {
+    intcode: Intcode,
+    breakpoints: HashSet<usize>,
+    opcode_breakpoints: HashSet<Opcode>,
+    // Last value seen at each watched address
+    watchpoints: HashMap<usize, i64>,
+}
+
+impl Debugger {
+    // Turns on journaling, unless the machine already keeps one, so it can go back
+    pub fn new(mut intcode: Intcode) -> Self {
+        if intcode.journal().is_none() {
+            intcode.set_journal(Some(Journal::default()));
+        }
+
+        Self {
+            intcode,
+            breakpoints: HashSet::new(),
+            opcode_breakpoints: HashSet::new(),
+            watchpoints: HashMap::new(),
+        }
+    }
+
+    pub fn intcode(&self) -> &Intcode {
+        &self.intcode
+    }
+
+    pub fn into_intcode(self) -> Intcode {
+        self.intcode
+    }
+
+    // Reads commands line by line until `quit` or the end of the input
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        mut reader: R,
+        mut writer: W,
+    ) -> Result<(), String> {
+        let write_error = |e: std::io::Error| format!("Failed to write: {}", e);
+
+        writeln!(writer, "{}", self.current_instruction()).map_err(write_error)?;
+
+        loop {
+            write!(writer, "{}", PROMPT).map_err(write_error)?;
+            writer.flush().map_err(write_error)?;
+
+            let mut line = String::new();
+            let read = reader
+                .read_line(&mut line)
+                .map_err(|e| format!("Failed to read command: {}", e))?;
+
+            let line = line.trim();
+            if read == 0 || line == "q" || line == "quit" {
+                break;
+            }
+
+            if line.is_empty() {
+                continue;
+            }
+
+            match self.execute(line) {
+                Ok(output) => writeln!(writer, "{}", output),
+                Err(e) => writeln!(writer, "Error: {}", e),
+            }
+            .map_err(write_error)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let arguments = words.collect::<Vec<&str>>();
+
+        match command {
+            "s" | "step" => {
+                let count = match arguments.first() {
+                    Some(raw_count) => parse_number(raw_count)?,
+                    None => 1,
+                };
+
+                self.resume(Some(count))
+            }
+            "c" | "continue" => self.resume(None),
+            "rs" | "rstep" => {
+                let count = match arguments.first() {
+                    Some(raw_count) => parse_number(raw_count)?,
+                    None => 1,
+                };
+
+                for _ in 0..count {
+                    self.intcode.step_back().map_err(|e| e.to_string())?;
+                }
+
+                Ok(self.arrived())
+            }
+            "rewind" => {
+                let address = parse_number(single(&arguments)?)?;
+                match self
+                    .intcode
+                    .rewind_to_write(address)
+                    .map_err(|e| e.to_string())?
+                {
+                    Some(step) => Ok(format!(
+                        "Last write to mem[{}] at step {}\n{}",
+                        address,
+                        step,
+                        self.arrived()
+                    )),
+                    None => Err(format!("No write to mem[{}] in the journal", address)),
+                }
+            }
+            "goto" => {
+                let step = parse_number(single(&arguments)?)? as u64;
+                let state = self.intcode.goto_step(step).map_err(|e| e.to_string())?;
+
+                let mut lines = self
+                    .intcode
+                    .drain_outputs()
+                    .into_iter()
+                    .map(|value| format!("Output: {}", value))
+                    .collect::<Vec<String>>();
+                match state {
+                    Some(RunState::Halted) => lines.push(String::from("Halted")),
+                    Some(RunState::NeedsInput) => {
+                        lines.push(String::from("Waiting for input"));
+                        lines.push(self.arrived());
+                    }
+                    _ => lines.push(self.arrived()),
+                }
+
+                Ok(lines.join("\n"))
+            }
+            "b" | "break" => self.add_breakpoint(single(&arguments)?),
+            "w" | "watch" => {
+                let address = parse_number(single(&arguments)?)?;
+                let value = self.intcode.memory(address);
+                self.watchpoints.insert(address, value);
+
+                Ok(format!("Watching mem[{}] (currently {})", address, value))
+            }
+            "clear" => {
+                self.breakpoints.clear();
+                self.opcode_breakpoints.clear();
+                self.watchpoints.clear();
+
+                Ok(String::from("Removed every breakpoint and watchpoint"))
+            }
+            "p" | "print" => self.print(&arguments.concat()),
+            "set" => self.set(&arguments.concat()),
+            "i" | "input" => {
+                let values = arguments
+                    .iter()
+                    .map(|a| {
+                        a.parse::<i64>()
+                            .map_err(|_| format!("Invalid input: {}", a))
+                    })
+                    .collect::<Result<Vec<i64>, String>>()?;
+                let count = values.len();
+                self.intcode.push_inputs(values);
+
+                Ok(format!("Queued {} input(s)", count))
+            }
+            "l" | "list" => {
+                let count = match arguments.first() {
+                    Some(raw_count) => parse_number(raw_count)?,
+                    None => 5,
+                };
+
+                Ok(self.list(count))
+            }
+            "h" | "help" => Ok(String::from(HELP)),
+            _ => Err(format!("Unknown command: {}", command)),
+        }
+    }
+
+    // Executes up to `limit` instructions, or until something worth stopping for happens
+    fn resume(&mut self, limit: Option<usize>) -> Result<String, String> {
+        let mut lines = Vec::new();
+        let mut executed = 0;
+
+        loop {
+            if Some(executed) == limit {
+                break;
+            }
+
+            // Never stop on the breakpoint we're resuming from
+            if executed > 0 {
+                if let Some(reason) = self.breakpoint() {
+                    lines.push(reason);
+                    break;
+                }
+            }
+
+            let state = self.intcode.step().map_err(|e| e.to_string())?;
+            executed += 1;
+
+            for value in self.intcode.drain_outputs() {
+                lines.push(format!("Output: {}", value));
+            }
+
+            let changes = self.watchpoint_changes();
+            let stop = !changes.is_empty();
+            lines.extend(changes);
+
+            match state {
+                Some(RunState::Halted) => {
+                    lines.push(String::from("Halted"));
+                    return Ok(lines.join("\n"));
+                }
+                Some(RunState::NeedsInput) => {
+                    lines.push(String::from("Waiting for input"));
+                    break;
+                }
+                _ if stop => break,
+                _ => {}
+            }
+        }
+
+        lines.push(self.current_instruction());
+        Ok(lines.join("\n"))
+    }
+
+    fn breakpoint(&self) -> Option<String> {
+        let position = self.intcode.position();
+
+        if self.breakpoints.contains(&position) {
+            return Some(format!("Breakpoint at {}", position));
+        }
+
+        match parse_opcode(self.intcode.memory(position)) {
+            Some(opcode) if self.opcode_breakpoints.contains(&opcode) => {
+                Some(format!("Breakpoint on {}", opcode.mnemonic()))
+            }
+            _ => None,
+        }
+    }
+
+    fn watchpoint_changes(&mut self) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        for (address, last_value) in self.watchpoints.iter_mut() {
+            let value = self.intcode.memory(*address);
+            if value != *last_value {
+                changes.push((
+                    *address,
+                    format!("Watchpoint mem[{}]: {} -> {}", address, last_value, value),
+                ));
+                *last_value = value;
+            }
+        }
+
+        changes.sort();
+        changes.into_iter().map(|(_, change)| change).collect()
+    }
+
+    fn add_breakpoint(&mut self, target: &str) -> Result<String, String> {
+        if let Ok(position) = target.parse::<usize>() {
+            self.breakpoints.insert(position);
+            return Ok(format!("Breakpoint set at {}", position));
+        }
+
+        match Opcode::from_mnemonic(target) {
+            Some(opcode) => {
+                self.opcode_breakpoints.insert(opcode);
+                Ok(format!("Breakpoint set on {}", opcode.mnemonic()))
+            }
+            None => Err(format!("Invalid breakpoint: {}", target)),
+        }
+    }
+
+    fn print(&self, target: &str) -> Result<String, String> {
+        match target {
+            "rb" => Ok(format!("rb = {}", self.intcode.relative_base())),
+            "ip" => Ok(format!("ip = {}", self.intcode.position())),
+            "steps" => Ok(format!("steps = {}", self.intcode.steps())),
+            _ => match parse_memory(target)? {
+                (address, None) => Ok(format!(
+                    "mem[{}] = {}",
+                    address,
+                    self.intcode.memory(address)
+                )),
+                (start, Some(end)) => {
+                    let values = (start..end)
+                        .map(|a| self.intcode.memory(a).to_string())
+                        .collect::<Vec<String>>();
+
+                    Ok(format!("mem[{}..{}] = [{}]", start, end, values.join(", ")))
+                }
+            },
+        }
+    }
+
+    fn set(&mut self, assignment: &str) -> Result<String, String> {
+        let (target, raw_value) = match assignment.find('=') {
+            Some(i) => (&assignment[..i], &assignment[i + 1..]),
+            None => return Err(format!("Invalid assignment: {}", assignment)),
+        };
+
+        let address = match parse_memory(target)? {
+            (address, None) => address,
+            _ => return Err(String::from("Only one address can be set at a time")),
+        };
+        let value = raw_value
+            .parse::<i64>()
+            .map_err(|_| format!("Invalid value: {}", raw_value))?;
+
+        self.intcode
+            .set_memory(address, value)
+            .map_err(|e| e.to_string())?;
+        if let Some(last_value) = self.watchpoints.get_mut(&address) {
+            *last_value = value;
+        }
+
+        Ok(format!("mem[{}] = {}", address, value))
+    }
+
+    fn list(&self, count: usize) -> String {
+        let memory = self.intcode.current_state();
+        let mut address = self.intcode.position();
+        let mut lines = Vec::new();
+
+        while lines.len() < count && address < memory.len() {
+            let line = decode(memory, address).unwrap_or(Line::Data {
+                address,
+                values: vec![memory[address]],
+            });
+
+            address += line.size();
+            lines.push(line.to_string());
+        }
+
+        lines.join("\n")
+    }
+
+    // After going somewhere other than forward, watchpoints start over from what's there now
+    fn arrived(&mut self) -> String {
+        for (address, value) in self.watchpoints.iter_mut() {
+            *value = self.intcode.memory(*address);
+        }
+
+        format!(
+            "Step {}: {}",
+            self.intcode.steps(),
+            self.current_instruction()
+        )
+    }
+
+    fn current_instruction(&self) -> String {
+        if self.intcode.halted() {
+            return String::from("Halted");
+        }
+
+        match self.list(1).as_str() {
+            "" => format!("{:04}: <end of program>", self.intcode.position()),
+            line => String::from(line),
+        }
+    }
+}
+
+fn single<'a>(arguments: &[&'a str]) -> Result<&'a str, String> {
+    match arguments {
+        [argument] => Ok(argument),
+        _ => Err(format!("Expected one argument, got {}", arguments.len())),
+    }
+}
+
+fn parse_number(raw_value: &str) -> Result<usize, String> {
+    raw_value
+        .parse::<usize>()
+        .map_err(|_| format!("Invalid number: {}", raw_value))
+}
+
+// `mem[A]` or `mem[A..B]`
+fn parse_memory(target: &str) -> Result<(usize, Option<usize>), String> {
+    let range = target
+        .strip_prefix("mem[")
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(|| format!("Invalid memory reference: {}", target))?;
+
+    match range.find("..") {
+        Some(i) => {
+            let start = parse_number(&range[..i])?;
+            let end = parse_number(&range[i + 2..])?;
+
+            if end < start {
+                return Err(format!("Invalid range: {}", range));
+            }
+
+            if end - start > MAX_RANGE {
+                return Err(format!(
+                    "Range {} is longer than {} cells",
+                    range, MAX_RANGE
+                ));
+            }
+
+            Ok((start, Some(end)))
+        }
+        None => Ok((parse_number(range)?, None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Doubles every input forever
+    const DOUBLER: &[i64] = &[3, 11, 1002, 11, 2, 11, 4, 11, 1105, 1, 0, 0];
+
+    #[test]
+    fn step() {
+        let mut debugger = Debugger::new(Intcode::new(&[1101, 2, 3, 7, 4, 7, 99, 0]));
+
+        assert_eq!(Ok(String::from("0004: OUT [7]")), debugger.execute("step"));
+        assert_eq!(
+            Ok(String::from("Output: 5\nHalted")),
+            debugger.execute("s 5")
+        );
+    }
+
+    #[test]
+    fn continue_until_input_and_inject_it() {
+        let mut debugger = Debugger::new(Intcode::new(DOUBLER));
+
+        assert_eq!(
+            Ok(String::from("Waiting for input\n0000: IN -> [11]")),
+            debugger.execute("continue")
+        );
+        assert_eq!(
+            Ok(String::from("Queued 2 input(s)")),
+            debugger.execute("input 4 5")
+        );
+        assert_eq!(
+            Ok(String::from(
+                "Output: 8\nOutput: 10\nWaiting for input\n0000: IN -> [11]"
+            )),
+            debugger.execute("c")
+        );
+    }
+
+    #[test]
+    fn breakpoints() {
+        let mut debugger = Debugger::new(Intcode::new(DOUBLER));
+        debugger.execute("input 1 2").unwrap();
+
+        assert_eq!(
+            Ok(String::from("Breakpoint set on OUT")),
+            debugger.execute("break out")
+        );
+        assert_eq!(
+            Ok(String::from("Breakpoint on OUT\n0006: OUT [11]")),
+            debugger.execute("c")
+        );
+
+        debugger.execute("clear").unwrap();
+        debugger.execute("b 2").unwrap();
+        assert_eq!(
+            Ok(String::from(
+                "Output: 2\nBreakpoint at 2\n0002: MUL [11], #2 -> [11]"
+            )),
+            debugger.execute("c")
+        );
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut debugger = Debugger::new(Intcode::new(DOUBLER));
+        debugger.execute("input 3").unwrap();
+
+        assert_eq!(
+            Ok(String::from("Watching mem[11] (currently 0)")),
+            debugger.execute("watch 11")
+        );
+        assert_eq!(
+            Ok(String::from(
+                "Watchpoint mem[11]: 0 -> 3\n0002: MUL [11], #2 -> [11]"
+            )),
+            debugger.execute("c")
+        );
+        assert_eq!(
+            Ok(String::from("Watchpoint mem[11]: 3 -> 6\n0006: OUT [11]")),
+            debugger.execute("c")
+        );
+    }
+
+    #[test]
+    fn print_and_set_memory() {
+        let mut debugger = Debugger::new(Intcode::new(&[109, 7, 99]));
+        debugger.execute("step").unwrap();
+
+        assert_eq!(Ok(String::from("rb = 7")), debugger.execute("print rb"));
+        assert_eq!(Ok(String::from("ip = 2")), debugger.execute("p ip"));
+        assert_eq!(
+            Ok(String::from("mem[4] = 12")),
+            debugger.execute("set mem[4] = 12")
+        );
+        assert_eq!(
+            Ok(String::from("mem[1..5] = [7, 99, 0, 12]")),
+            debugger.execute("print mem[1..5]")
+        );
+        assert!(debugger.execute("print mem[5..1]").is_err());
+        assert_eq!(
+            Err(String::from(
+                "Range 0..1000000000 is longer than 1000 cells"
+            )),
+            debugger.execute("print mem[0..1000000000]")
+        );
+        assert!(debugger.execute("print mem[0..1000]").is_ok());
+    }
+
+    #[test]
+    fn reverse_step_and_goto() {
+        let mut debugger = Debugger::new(Intcode::new(DOUBLER));
+        debugger.execute("input 3 4").unwrap();
+        debugger.execute("s 5").unwrap();
+
+        assert_eq!(
+            Ok(String::from("Step 3: 0008: JNZ #1, #0")),
+            debugger.execute("rstep 2")
+        );
+        assert_eq!(
+            Ok(String::from("mem[11] = 6")),
+            debugger.execute("p mem[11]")
+        );
+        assert_eq!(
+            Ok(String::from("Output: 8\nStep 7: 0008: JNZ #1, #0")),
+            debugger.execute("goto 7")
+        );
+        assert_eq!(
+            Ok(String::from("Step 0: 0000: IN -> [11]")),
+            debugger.execute("goto 0")
+        );
+        assert_eq!(
+            Err(String::from("Step 0 is not in the journal")),
+            debugger.execute("rs")
+        );
+    }
+
+    #[test]
+    fn goto_past_the_end_keeps_outputs() {
+        let mut debugger = Debugger::new(Intcode::new(&[1101, 2, 3, 7, 4, 7, 99, 0]));
+
+        assert_eq!(
+            Ok(String::from("Output: 5\nHalted")),
+            debugger.execute("goto 10")
+        );
+        assert_eq!(
+            Ok(String::from("Step 1: 0004: OUT [7]")),
+            debugger.execute("goto 1")
+        );
+    }
+
+    #[test]
+    fn rewind_to_the_last_write() {
+        let mut debugger = Debugger::new(Intcode::new(DOUBLER));
+        debugger.execute("input 3 4").unwrap();
+        debugger.execute("s 9").unwrap();
+        debugger.execute("watch 11").unwrap();
+
+        assert_eq!(
+            Ok(String::from(
+                "Last write to mem[11] at step 5\nStep 5: 0002: MUL [11], #2 -> [11]"
+            )),
+            debugger.execute("rewind 11")
+        );
+        assert_eq!(Ok(String::from("steps = 5")), debugger.execute("p steps"));
+        assert_eq!(
+            Ok(String::from("Watchpoint mem[11]: 4 -> 8\n0006: OUT [11]")),
+            debugger.execute("c")
+        );
+        assert!(debugger.execute("rewind 3").is_err());
+    }
+
+    #[test]
+    fn list() {
+        let debugger = Debugger::new(Intcode::new(DOUBLER));
+        let expected = "\
+0000: IN -> [11]
+0002: MUL [11], #2 -> [11]";
+
+        assert_eq!(expected, debugger.list(2));
+    }
+
+    #[test]
+    fn run_transcript() {
+        let mut debugger = Debugger::new(Intcode::new(&[104, 42, 99]));
+        let mut output = Vec::new();
+
+        debugger
+            .run("bogus\nstep\n\nquit\nstep\n".as_bytes(), &mut output)
+            .unwrap();
+
+        let expected = "\
+0000: OUT #42
+(intcode) Error: Unknown command: bogus
+(intcode) Output: 42
+0002: HALT
+(intcode) (intcode) ";
+
+        assert_eq!(expected, String::from_utf8(output).unwrap());
+    }
+}
@@ -0,0 +1,236 @@
+use std::fmt;
+
+use crate::operation::{parse_operation, Opcode, ParameterMode};
+
+// Runs of data are split into lines of at most this many values
+const DATA_PER_LINE: usize = 8;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Parameter {
+    pub mode: ParameterMode,
+    pub value: i64,
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            ParameterMode::Position => write!(f, "[{}]", self.value),
+            ParameterMode::Immediate => write!(f, "#{}", self.value),
+            ParameterMode::Relative if self.value < 0 => write!(f, "[rb{}]", self.value),
+            ParameterMode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Line {
+    Instruction {
+        address: usize,
+        opcode: Opcode,
+        parameters: Vec<Parameter>,
+    },
+    Data {
+        address: usize,
+        values: Vec<i64>,
+    },
+}
+
+impl Line {
+    pub fn address(&self) -> usize {
+        match self {
+            Line::Instruction { address, .. } | Line::Data { address, .. } => *address,
+        }
+    }
+
+    // Number of memory cells the line covers
+    pub fn size(&self) -> usize {
+        match self {
+            Line::Instruction { parameters, .. } => parameters.len() + 1,
+            Line::Data { values, .. } => values.len(),
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Instruction {
+                address,
+                opcode,
+                parameters,
+            } => {
+                write!(f, "{:04}: {}", address, opcode.mnemonic())?;
+
+                let result = opcode.result_parameter();
+                let inputs = parameters
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| Some(*i) != result)
+                    .map(|(_, p)| p.to_string())
+                    .collect::<Vec<String>>();
+
+                if !inputs.is_empty() {
+                    write!(f, " {}", inputs.join(", "))?;
+                }
+
+                if let Some(parameter) = result.and_then(|i| parameters.get(i)) {
+                    write!(f, " -> {}", parameter)?;
+                }
+
+                Ok(())
+            }
+            Line::Data { address, values } => {
+                let values = values
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<String>>();
+
+                write!(f, "{:04}: DATA {}", address, values.join(", "))
+            }
+        }
+    }
+}
+
+// Decodes the instruction at `address`, if the cells there form a complete, valid one
+pub fn decode(program: &[i64], address: usize) -> Option<Line> {
+    let operation = *program.get(address)?;
+    let (opcode, first_mode, second_mode, third_mode) = parse_operation(address, operation).ok()?;
+    let modes = [first_mode, second_mode, third_mode];
+
+    let parameters = (0..opcode.parameter_count())
+        .map(|i| {
+            program.get(address + i + 1).map(|value| Parameter {
+                mode: modes[i],
+                value: *value,
+            })
+        })
+        .collect::<Option<Vec<Parameter>>>()?;
+
+    if let Some(i) = opcode.result_parameter() {
+        if parameters[i].mode == ParameterMode::Immediate {
+            return None;
+        }
+    }
+
+    Some(Line::Instruction {
+        address,
+        opcode,
+        parameters,
+    })
+}
+
+// Linear sweep over the program; cells that don't decode are marked as data
+pub fn disassemble(program: &[i64]) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    while address < program.len() {
+        if let Some(line) = decode(program, address) {
+            address += line.size();
+            lines.push(line);
+            continue;
+        }
+
+        let value = program[address];
+        match lines.last_mut() {
+            Some(Line::Data { values, .. }) if values.len() < DATA_PER_LINE => values.push(value),
+            _ => lines.push(Line::Data {
+                address,
+                values: vec![value],
+            }),
+        }
+
+        address += 1;
+    }
+
+    lines
+}
+
+pub fn listing(program: &[i64]) -> String {
+    disassemble(program)
+        .iter()
+        .map(|l| l.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_instruction() {
+        let line = Line::Instruction {
+            address: 42,
+            opcode: Opcode::Add,
+            parameters: vec![
+                Parameter {
+                    mode: ParameterMode::Relative,
+                    value: 3,
+                },
+                Parameter {
+                    mode: ParameterMode::Immediate,
+                    value: 5,
+                },
+                Parameter {
+                    mode: ParameterMode::Position,
+                    value: 100,
+                },
+            ],
+        };
+
+        assert_eq!("0042: ADD [rb+3], #5 -> [100]", line.to_string());
+    }
+
+    #[test]
+    fn listing_marks_data() {
+        let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let expected = "\
+0000: IN -> [9]
+0002: EQ [9], [10] -> [9]
+0006: OUT [9]
+0008: HALT
+0009: DATA -1, 8";
+
+        assert_eq!(expected, listing(&program));
+    }
+
+    #[test]
+    fn listing_relative_parameters() {
+        let program = vec![109, -1, 204, -3, 21101, 1, 2, 0, 99];
+        let expected = "\
+0000: ARB #-1
+0002: OUT [rb-3]
+0004: ADD #1, #2 -> [rb+0]
+0008: HALT";
+
+        assert_eq!(expected, listing(&program));
+    }
+
+    #[test]
+    fn writes_in_immediate_mode_are_data() {
+        assert_eq!(
+            vec![Line::Data {
+                address: 0,
+                values: vec![11101, 1, 1, 0],
+            }],
+            disassemble(&[11101, 1, 1, 0])
+        );
+    }
+
+    #[test]
+    fn truncated_instruction_is_data() {
+        let lines = disassemble(&[99, 1, 2]);
+
+        assert_eq!("0001: DATA 1, 2", lines[1].to_string());
+        assert_eq!(3, lines.iter().map(|l| l.size()).sum::<usize>());
+    }
+
+    #[test]
+    fn long_data_runs_are_split() {
+        let lines = disassemble(&[0; 10]);
+
+        assert_eq!(2, lines.len());
+        assert_eq!(8, lines[1].address());
+    }
+}
@@ -0,0 +1,89 @@
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum IntcodeError {
+    // `value` is the whole instruction, as found in memory
+    InvalidOpcode { ip: usize, value: i64 },
+    InvalidMode { ip: usize, value: i64 },
+    NegativeAddress { ip: usize, address: i64 },
+    // An addition or multiplication, or a relative address, doesn't fit in an i64
+    Overflow { ip: usize },
+    WriteInImmediateMode { ip: usize },
+    InputExhausted { ip: usize },
+    StepLimitExceeded { limit: u64 },
+    TimeLimitExceeded { limit: Duration },
+    // The machine came back to a state it had already been in without doing any I/O
+    InfiniteLoop { ip: usize },
+    MemoryLimitExceeded { address: usize, limit: usize },
+    NoOutput,
+    // Going back to `step` needs a journal that reaches that far
+    HistoryUnavailable { step: u64 },
+    UnknownNode(String),
+    Device(String),
+    Tracer(String),
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntcodeError::InvalidOpcode { ip, value } => {
+                write!(f, "Invalid opcode in {} at position {}", value, ip)
+            }
+            IntcodeError::InvalidMode { ip, value } => {
+                write!(f, "Invalid param mode in {} at position {}", value, ip)
+            }
+            IntcodeError::NegativeAddress { ip, address } => {
+                write!(f, "Negative address {} at position {}", address, ip)
+            }
+            IntcodeError::Overflow { ip } => write!(f, "Arithmetic overflow at position {}", ip),
+            IntcodeError::WriteInImmediateMode { ip } => {
+                write!(f, "Write in immediate mode at position {}", ip)
+            }
+            IntcodeError::InputExhausted { ip } => {
+                write!(f, "Ran out of input at position {}", ip)
+            }
+            IntcodeError::StepLimitExceeded { limit } => {
+                write!(f, "Exceeded the limit of {} steps", limit)
+            }
+            IntcodeError::TimeLimitExceeded { limit } => {
+                write!(f, "Exceeded the time limit of {:?}", limit)
+            }
+            IntcodeError::InfiniteLoop { ip } => {
+                write!(f, "Infinite loop detected at position {}", ip)
+            }
+            IntcodeError::MemoryLimitExceeded { address, limit } => write!(
+                f,
+                "Writing to {} would exceed the limit of {} memory cells",
+                address, limit
+            ),
+            IntcodeError::NoOutput => write!(f, "Program produced no output"),
+            IntcodeError::HistoryUnavailable { step } => {
+                write!(f, "Step {} is not in the journal", step)
+            }
+            IntcodeError::UnknownNode(name) => write!(f, "No node named {} in the network", name),
+            IntcodeError::Device(message) => write!(f, "Device error: {}", message),
+            IntcodeError::Tracer(message) => write!(f, "Tracer error: {}", message),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            "Invalid opcode in 42 at position 7",
+            IntcodeError::InvalidOpcode { ip: 7, value: 42 }.to_string()
+        );
+        assert_eq!(
+            "Negative address -3 at position 0",
+            IntcodeError::NegativeAddress { ip: 0, address: -3 }.to_string()
+        );
+    }
+}
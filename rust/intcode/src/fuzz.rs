@@ -0,0 +1,736 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::compiler::Engine;
+use crate::error::IntcodeError;
+use crate::limits::Limits;
+use crate::machine::{Intcode, RunState};
+use crate::memory::mix;
+use crate::operation::{Opcode, ParameterMode};
+use crate::snapshot::Snapshot;
+
+// Lets generated programs loop for a while without making thousands of them slow to run
+const STEP_LIMIT: u64 = 10_000;
+
+// Every way the machine can execute a program; they must all end in the same state, the state
+// the reference evaluator below ends in
+const SETUPS: [(&str, Engine, bool); 3] = [
+    ("interpreter", Engine::Interpreter, true),
+    (
+        "interpreter without decode cache",
+        Engine::Interpreter,
+        false,
+    ),
+    ("compiled", Engine::Compiled, true),
+];
+
+// The opcodes a day's puzzle had introduced by then
+pub fn day_opcodes(day: u8) -> Option<Vec<Opcode>> {
+    let mut opcodes = vec![Opcode::Add, Opcode::Multiply, Opcode::Halt];
+    let io_and_jumps = [
+        Opcode::Set,
+        Opcode::Output,
+        Opcode::JumpIfTrue,
+        Opcode::JumpIfFalse,
+        Opcode::LessThan,
+        Opcode::Equals,
+    ];
+
+    match day {
+        2 => {}
+        5 | 7 => opcodes.extend(&io_and_jumps),
+        9 => {
+            opcodes.extend(&io_and_jumps);
+            opcodes.push(Opcode::SetRelativeBase);
+        }
+        _ => return None,
+    }
+
+    Some(opcodes)
+}
+
+// How one setup finished running a program. Memory is listed cell by cell, as setups lay it out
+// differently. A panic is always a bug, however many setups agree on it.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Outcome {
+    Finished {
+        result: Result<RunState, IntcodeError>,
+        state: Snapshot,
+    },
+    Panicked(String),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Finished { result, state } => {
+                match result {
+                    Ok(run_state) => write!(f, "{:?}", run_state)?,
+                    Err(e) => write!(f, "{}", e)?,
+                }
+
+                write!(
+                    f,
+                    " after {} steps at {}, rb {}, outputs {:?}, memory hash {:016x}",
+                    state.steps,
+                    state.position,
+                    state.relative_base,
+                    state.outputs,
+                    hash(state)
+                )
+            }
+            Outcome::Panicked(message) => write!(f, "panicked: {}", message),
+        }
+    }
+}
+
+// A program the setups disagree on, or one that makes any of them panic
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mismatch {
+    pub program: Vec<i64>,
+    pub inputs: Vec<i64>,
+    pub outcomes: Vec<(&'static str, Outcome)>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Program: {}", join(&self.program))?;
+        writeln!(f, "Inputs: {}", join(&self.inputs))?;
+
+        for (name, outcome) in &self.outcomes {
+            writeln!(f, "{}: {}", name, outcome)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FuzzError {
+    // Programs can't be generated without any opcodes to make them of
+    NoOpcodes,
+    Mismatch(Mismatch),
+}
+
+impl fmt::Display for FuzzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FuzzError::NoOpcodes => write!(f, "No opcodes to generate programs from"),
+            FuzzError::Mismatch(mismatch) => write!(f, "{}", mismatch),
+        }
+    }
+}
+
+// Generates random valid programs and checks every setup agrees on them, shrinking the first
+// one they don't agree on
+pub struct Fuzzer {
+    seed: u64,
+    opcodes: Vec<Opcode>,
+    runs: u64,
+    instructions: usize,
+}
+
+impl Fuzzer {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            opcodes: day_opcodes(9).unwrap(),
+            runs: 1000,
+            instructions: 24,
+        }
+    }
+
+    pub fn opcodes(mut self, opcodes: &[Opcode]) -> Self {
+        self.opcodes = opcodes.to_vec();
+        self
+    }
+
+    pub fn runs(mut self, runs: u64) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    // Longest program generated, in instructions
+    pub fn instructions(mut self, instructions: usize) -> Self {
+        self.instructions = instructions.max(1);
+        self
+    }
+
+    // The number of programs run, all of which every setup agreed on
+    pub fn run(&self) -> Result<u64, FuzzError> {
+        if self.opcodes.is_empty() {
+            return Err(FuzzError::NoOpcodes);
+        }
+
+        let mut rng = Rng::new(self.seed);
+        let reads_input = self.opcodes.contains(&Opcode::Set);
+
+        for _ in 0..self.runs {
+            let length = rng.below(self.instructions) + 1;
+            let program = generate(&mut rng, &self.opcodes, length);
+            let inputs = if reads_input {
+                (0..rng.below(5)).map(|_| rng.between(-10, 10)).collect()
+            } else {
+                Vec::new()
+            };
+
+            if compare(&program, &inputs).is_some() {
+                let (program, inputs) = shrink(&program, &inputs, |p, i| compare(p, i).is_some());
+                let mismatch = compare(&program, &inputs).expect("Shrinking lost the mismatch");
+                return Err(FuzzError::Mismatch(mismatch));
+            }
+        }
+
+        Ok(self.runs)
+    }
+}
+
+// Runs the program with every setup and the reference, returning their outcomes if they don't
+// all agree or any of them panicked
+pub fn compare(program: &[i64], inputs: &[i64]) -> Option<Mismatch> {
+    let mut outcomes = SETUPS
+        .iter()
+        .map(|&(name, engine, cached)| (name, outcome(program, inputs, engine, cached)))
+        .collect::<Vec<(&'static str, Outcome)>>();
+
+    // The reference has no memory limit, so it can't say where the machine runs into it
+    let out_of_memory = match &outcomes[0].1 {
+        Outcome::Finished { result, .. } => {
+            matches!(result, Err(IntcodeError::MemoryLimitExceeded { .. }))
+        }
+        Outcome::Panicked(_) => false,
+    };
+    if !out_of_memory {
+        outcomes.push((
+            "reference",
+            caught(|| Reference::new(program, inputs).run()),
+        ));
+    }
+
+    mismatch(program, inputs, outcomes)
+}
+
+fn mismatch(
+    program: &[i64],
+    inputs: &[i64],
+    outcomes: Vec<(&'static str, Outcome)>,
+) -> Option<Mismatch> {
+    let agree = outcomes.iter().all(|(_, outcome)| match outcome {
+        Outcome::Finished { .. } => *outcome == outcomes[0].1,
+        Outcome::Panicked(_) => false,
+    });
+    if agree {
+        return None;
+    }
+
+    Some(Mismatch {
+        program: program.to_vec(),
+        inputs: inputs.to_vec(),
+        outcomes,
+    })
+}
+
+fn outcome(program: &[i64], inputs: &[i64], engine: Engine, cached: bool) -> Outcome {
+    caught(|| {
+        let mut intcode = Intcode::new(program);
+        intcode.set_limits(Limits {
+            steps: Some(STEP_LIMIT),
+            time: None,
+            detect_loops: false,
+        });
+        intcode.set_engine(engine);
+        intcode.set_decode_cache(cached);
+        intcode.push_inputs(inputs.iter().copied());
+
+        let result = intcode.run();
+        (result, cell_by_cell(intcode.snapshot()))
+    })
+}
+
+fn caught<F>(run: F) -> Outcome
+where
+    F: FnOnce() -> (Result<RunState, IntcodeError>, Snapshot),
+{
+    match panic::catch_unwind(AssertUnwindSafe(run)) {
+        Ok((result, state)) => Outcome::Finished { result, state },
+        Err(payload) => Outcome::Panicked(
+            payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default(),
+        ),
+    }
+}
+
+// Every non-zero cell in `sparse`, wherever the machine kept it
+fn cell_by_cell(mut state: Snapshot) -> Snapshot {
+    let mut cells = state
+        .memory
+        .drain(..)
+        .enumerate()
+        .chain(state.sparse.drain(..))
+        .filter(|(_, value)| *value != 0)
+        .collect::<Vec<(usize, i64)>>();
+    cells.sort_unstable();
+
+    Snapshot {
+        sparse: cells,
+        ..state
+    }
+}
+
+// A second machine, as plain as can be, for the setups to be checked against: memory is a map
+// and every instruction is decoded afresh. It runs every opcode `day_opcodes` hands out.
+struct Reference {
+    memory: BTreeMap<usize, i64>,
+    // One past the highest address written to, or the end of the program
+    len: usize,
+    ip: usize,
+    relative_base: i64,
+    inputs: VecDeque<i64>,
+    outputs: Vec<i64>,
+    last_output: Option<i64>,
+    halted: bool,
+    steps: u64,
+}
+
+impl Reference {
+    fn new(program: &[i64], inputs: &[i64]) -> Self {
+        Self {
+            memory: program.iter().copied().enumerate().collect(),
+            len: program.len(),
+            ip: 0,
+            relative_base: 0,
+            inputs: inputs.iter().copied().collect(),
+            outputs: Vec::new(),
+            last_output: None,
+            halted: false,
+            steps: 0,
+        }
+    }
+
+    fn run(mut self) -> (Result<RunState, IntcodeError>, Snapshot) {
+        let result = loop {
+            match self.step() {
+                Ok(None) => {}
+                Ok(Some(state)) => break Ok(state),
+                Err(e) => break Err(e),
+            }
+        };
+
+        let state = Snapshot {
+            memory: Vec::new(),
+            sparse: self
+                .memory
+                .iter()
+                .filter(|(_, value)| **value != 0)
+                .map(|(address, value)| (*address, *value))
+                .collect(),
+            position: self.ip,
+            relative_base: self.relative_base,
+            inputs: self.inputs.iter().copied().collect(),
+            outputs: self.outputs,
+            last_output: self.last_output,
+            halted: self.halted,
+            steps: self.steps,
+        };
+
+        (result, state)
+    }
+
+    fn step(&mut self) -> Result<Option<RunState>, IntcodeError> {
+        let ip = self.ip;
+        if ip >= self.len {
+            self.halted = true;
+            return Ok(Some(RunState::Halted));
+        }
+
+        if self.steps >= STEP_LIMIT {
+            return Err(IntcodeError::StepLimitExceeded { limit: STEP_LIMIT });
+        }
+
+        let value = self.read(ip);
+        let opcode = value % 100;
+        if !(1..=9).contains(&opcode) && opcode != 99 {
+            return Err(IntcodeError::InvalidOpcode { ip, value });
+        }
+        if (2..5).any(|digit| value / 10i64.pow(digit) % 10 > 2) {
+            return Err(IntcodeError::InvalidMode { ip, value });
+        }
+
+        match opcode {
+            1 | 2 | 7 | 8 => {
+                let (a, b) = (self.value(1)?, self.value(2)?);
+                let address = self.address(3)?;
+                let result = match opcode {
+                    1 => a.checked_add(b),
+                    2 => a.checked_mul(b),
+                    7 => Some((a < b) as i64),
+                    _ => Some((a == b) as i64),
+                };
+                let result = result.ok_or(IntcodeError::Overflow { ip })?;
+
+                self.write(address, result);
+                self.ip += 4;
+            }
+            3 => {
+                let address = self.address(1)?;
+                match self.inputs.pop_front() {
+                    Some(input) => self.write(address, input),
+                    None => return Ok(Some(RunState::NeedsInput)),
+                }
+
+                self.ip += 2;
+            }
+            4 => {
+                let output = self.value(1)?;
+                self.outputs.push(output);
+                self.last_output = Some(output);
+                self.ip += 2;
+            }
+            5 | 6 => {
+                let (condition, target) = (self.value(1)?, self.value(2)?);
+                if (condition != 0) == (opcode == 5) {
+                    if target < 0 {
+                        return Err(IntcodeError::NegativeAddress {
+                            ip,
+                            address: target,
+                        });
+                    }
+
+                    self.ip = target as usize;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            9 => {
+                let adjustment = self.value(1)?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(adjustment)
+                    .ok_or(IntcodeError::Overflow { ip })?;
+                self.ip += 2;
+            }
+            _ => self.halted = true,
+        }
+
+        self.steps += 1;
+        Ok(self.halted.then_some(RunState::Halted))
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.memory.get(&address).copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, address: usize, value: i64) {
+        self.memory.insert(address, value);
+        self.len = self.len.max(address + 1);
+    }
+
+    fn mode(&self, parameter: usize) -> i64 {
+        self.read(self.ip) / 10i64.pow(parameter as u32 + 1) % 10
+    }
+
+    // Where the parameter points
+    fn address(&self, parameter: usize) -> Result<usize, IntcodeError> {
+        let ip = self.ip;
+        let value = self.read(ip + parameter);
+        let address = match self.mode(parameter) {
+            0 => value,
+            2 => self
+                .relative_base
+                .checked_add(value)
+                .ok_or(IntcodeError::Overflow { ip })?,
+            _ => return Err(IntcodeError::WriteInImmediateMode { ip }),
+        };
+
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress { ip, address });
+        }
+
+        Ok(address as usize)
+    }
+
+    fn value(&self, parameter: usize) -> Result<i64, IntcodeError> {
+        match self.mode(parameter) {
+            1 => Ok(self.read(self.ip + parameter)),
+            _ => Ok(self.read(self.address(parameter)?)),
+        }
+    }
+}
+
+// `instructions` random instructions followed by a HALT and a few cells of data. Parameters
+// mostly point at the data, but sometimes at code or past the end of the program.
+pub(crate) fn generate(rng: &mut Rng, opcodes: &[Opcode], instructions: usize) -> Vec<i64> {
+    let mut chosen = (0..instructions)
+        .map(|_| opcodes[rng.below(opcodes.len())])
+        .collect::<Vec<Opcode>>();
+    chosen.push(Opcode::Halt);
+
+    let starts = chosen
+        .iter()
+        .scan(0, |address, opcode| {
+            let start = *address;
+            *address += opcode.parameter_count() + 1;
+            Some(start)
+        })
+        .collect::<Vec<usize>>();
+    let code = starts[starts.len() - 1] + 1;
+    let size = code + rng.below(8) + 1;
+    let relative = opcodes.contains(&Opcode::SetRelativeBase);
+
+    let mut program = Vec::with_capacity(size);
+    for opcode in chosen {
+        let mut instruction = opcode.code();
+        let mut parameters = Vec::new();
+
+        for i in 0..opcode.parameter_count() {
+            let is_result = opcode.result_parameter() == Some(i);
+            let is_target =
+                (opcode == Opcode::JumpIfTrue || opcode == Opcode::JumpIfFalse) && i == 1;
+
+            let mode = match rng.below(if relative { 3 } else { 2 }) {
+                0 => ParameterMode::Position,
+                1 if is_result => ParameterMode::Position,
+                1 => ParameterMode::Immediate,
+                _ => ParameterMode::Relative,
+            };
+            let value = match mode {
+                ParameterMode::Immediate if is_target => starts[rng.below(starts.len())] as i64,
+                ParameterMode::Immediate => rng.between(-10, 10),
+                ParameterMode::Position => match rng.below(10) {
+                    0 => rng.below(code) as i64,
+                    1 => (size + rng.below(16)) as i64,
+                    _ => (code + rng.below(size - code)) as i64,
+                },
+                ParameterMode::Relative => rng.between(-4, size as i64),
+            };
+
+            instruction += mode.code() * 10i64.pow(i as u32 + 2);
+            parameters.push(value);
+        }
+
+        program.push(instruction);
+        program.extend(parameters);
+    }
+
+    while program.len() < size {
+        program.push(rng.between(-10, 10));
+    }
+
+    program
+}
+
+// Simplifies a program and its inputs for as long as `fails` holds, so a mismatch is reported
+// with as little as possible around it
+pub fn shrink<F>(program: &[i64], inputs: &[i64], fails: F) -> (Vec<i64>, Vec<i64>)
+where
+    F: Fn(&[i64], &[i64]) -> bool,
+{
+    let mut current = (program.to_vec(), inputs.to_vec());
+
+    while let Some(simpler) = simplifications(&current.0, &current.1)
+        .into_iter()
+        .find(|(program, inputs)| fails(program, inputs))
+    {
+        current = simpler;
+    }
+
+    current
+}
+
+// Every candidate is strictly smaller than what it came from, so shrinking always ends
+fn simplifications(program: &[i64], inputs: &[i64]) -> Vec<(Vec<i64>, Vec<i64>)> {
+    let mut candidates = Vec::new();
+
+    // Running off the end of memory halts, so cutting the end off is like ending in a HALT
+    for length in &[program.len() / 2, program.len().saturating_sub(1)] {
+        if *length < program.len() {
+            candidates.push((program[..*length].to_vec(), inputs.to_vec()));
+        }
+    }
+
+    for i in 0..inputs.len() {
+        let mut fewer = inputs.to_vec();
+        fewer.remove(i);
+        candidates.push((program.to_vec(), fewer));
+    }
+
+    for (i, &value) in program.iter().enumerate() {
+        for simpler in &[0, value / 2, 99] {
+            if size(*simpler) < size(value) {
+                let mut program = program.to_vec();
+                program[i] = *simpler;
+                candidates.push((program, inputs.to_vec()));
+            }
+        }
+    }
+
+    for (i, &value) in inputs.iter().enumerate() {
+        if value != 0 {
+            let mut inputs = inputs.to_vec();
+            inputs[i] = value / 2;
+            candidates.push((program.to_vec(), inputs));
+        }
+    }
+
+    candidates
+}
+
+fn size(value: i64) -> u64 {
+    value.unsigned_abs()
+}
+
+// splitmix64, so runs can be repeated from their seed
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(1);
+        mix(self.0)
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    pub(crate) fn between(&mut self, low: i64, high: i64) -> i64 {
+        low + (self.next() % (high - low + 1) as u64) as i64
+    }
+}
+
+fn hash(state: &Snapshot) -> u64 {
+    state
+        .memory
+        .iter()
+        .enumerate()
+        .map(|(address, value)| (address, *value))
+        .chain(state.sparse.iter().copied())
+        .fold(0, |hash, (address, value)| {
+            mix(hash ^ mix(address as u64) ^ value as u64)
+        })
+}
+
+fn join(values: &[i64]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::{decode, Line};
+
+    #[test]
+    fn generated_programs_are_valid() {
+        let mut rng = Rng(7);
+        let opcodes = day_opcodes(5).unwrap();
+
+        for _ in 0..100 {
+            let program = generate(&mut rng, &opcodes, 10);
+            let mut address = 0;
+
+            for i in 0..=10 {
+                let line = decode(&program, address).expect("Invalid instruction");
+                match &line {
+                    Line::Instruction { opcode, .. } if i == 10 => {
+                        assert_eq!(Opcode::Halt, *opcode)
+                    }
+                    Line::Instruction { opcode, .. } => assert!(opcodes.contains(opcode)),
+                    Line::Data { .. } => unreachable!(),
+                }
+
+                address += line.size();
+            }
+
+            assert!(program.len() > address);
+        }
+    }
+
+    #[test]
+    fn setups_agree() {
+        for day in &[2, 5, 7, 9] {
+            let fuzzer = Fuzzer::new(u64::from(*day))
+                .opcodes(&day_opcodes(*day).unwrap())
+                .runs(200);
+
+            assert_eq!(Ok(200), fuzzer.run());
+        }
+    }
+
+    #[test]
+    fn no_opcodes_are_rejected() {
+        assert_eq!(Err(FuzzError::NoOpcodes), Fuzzer::new(1).opcodes(&[]).run());
+    }
+
+    #[test]
+    fn the_reference_runs_the_examples() {
+        let run = |program: &[i64], inputs: &[i64]| Reference::new(program, inputs).run();
+
+        let (result, state) = run(&[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8], &[8]);
+        assert_eq!(Ok(RunState::Halted), result);
+        assert_eq!(vec![1], state.outputs);
+
+        let (result, state) = run(&[109, 19, 204, -15, 99], &[]);
+        assert_eq!(Ok(RunState::Halted), result);
+        assert_eq!(vec![99], state.outputs);
+        assert_eq!(19, state.relative_base);
+
+        let (result, state) = run(&[3, 0, 99], &[]);
+        assert_eq!(Ok(RunState::NeedsInput), result);
+        assert_eq!(0, state.steps);
+    }
+
+    #[test]
+    fn mismatches_are_reported() {
+        assert_eq!(None, compare(&[1101, 2, 3, 5, 99, 0], &[]));
+        assert_eq!(None, compare(&[1101, 0, 0, 1 << 40, 99], &[]));
+        // Found by fuzzing: the compiled engine skipped reading the target of a jump not taken
+        assert_eq!(None, compare(&[4, 0, 1102, 0, 0, 13, 2106, 1, -1], &[]));
+
+        // Panics fail even when every setup panics alike
+        let panicked = vec![
+            ("one", Outcome::Panicked(String::from("overflow"))),
+            ("two", Outcome::Panicked(String::from("overflow"))),
+        ];
+        assert!(mismatch(&[104, 1, 99], &[], panicked).is_some());
+
+        let mismatch = Mismatch {
+            program: vec![104, 1, 99],
+            inputs: vec![],
+            outcomes: vec![("one", Outcome::Panicked(String::from("overflow")))],
+        };
+        assert_eq!(
+            "Program: 104,1,99\nInputs: \none: panicked: overflow\n",
+            mismatch.to_string()
+        );
+    }
+
+    #[test]
+    fn shrink_keeps_what_fails() {
+        // Stands in for a bug where outputting 6 goes wrong
+        let outputs_six = |program: &[i64], inputs: &[i64]| {
+            let mut intcode = Intcode::new(program);
+            intcode.push_inputs(inputs.iter().copied());
+            intcode.run().is_ok() && intcode.drain_outputs().contains(&6)
+        };
+        let program = vec![3, 13, 1001, 13, 3, 13, 4, 13, 1101, 7, 8, 14, 99, 0, 0];
+
+        let (program, inputs) = shrink(&program, &[3, 9], outputs_six);
+
+        assert!(outputs_six(&program, &inputs));
+        assert_eq!(vec![3], inputs);
+        // The HALT stays, as writing to 13 grew memory past the end of the program
+        assert_eq!(vec![3, 0, 1001, 0, 3, 13, 4, 13, 99], program);
+    }
+}
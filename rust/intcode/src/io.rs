@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, StdinLock, Stdout, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+// Peripheral attached to a machine's `Set` and `Output` instructions
+pub trait IoDevice {
+    // Returning `None` pauses the machine with `RunState::NeedsInput`
+    fn read(&mut self) -> Result<Option<i64>, String>;
+
+    fn write(&mut self, value: i64) -> Result<(), String>;
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Queues {
+    pub(crate) inputs: VecDeque<i64>,
+    pub(crate) outputs: VecDeque<i64>,
+}
+
+impl IoDevice for Queues {
+    fn read(&mut self) -> Result<Option<i64>, String> {
+        Ok(self.inputs.pop_front())
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), String> {
+        self.outputs.push_back(value);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VecDevice {
+    inputs: VecDeque<i64>,
+    outputs: Vec<i64>,
+}
+
+impl VecDevice {
+    pub fn new(inputs: Vec<i64>) -> Self {
+        Self {
+            inputs: inputs.into(),
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn outputs(&self) -> &Vec<i64> {
+        &self.outputs
+    }
+
+    pub fn into_outputs(self) -> Vec<i64> {
+        self.outputs
+    }
+}
+
+impl IoDevice for VecDevice {
+    fn read(&mut self) -> Result<Option<i64>, String> {
+        Ok(self.inputs.pop_front())
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), String> {
+        self.outputs.push(value);
+        Ok(())
+    }
+}
+
+// Reads one integer per line and writes one integer per line
+pub struct StdioDevice<R: BufRead, W: Write> {
+    reader: R,
+    writer: W,
+}
+
+impl StdioDevice<StdinLock<'static>, Stdout> {
+    pub fn stdio() -> Self {
+        Self::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> StdioDevice<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+impl<R: BufRead, W: Write> IoDevice for StdioDevice<R, W> {
+    fn read(&mut self) -> Result<Option<i64>, String> {
+        loop {
+            let mut line = String::new();
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .map_err(|e| format!("Failed to read input: {}", e))?;
+
+            if read == 0 {
+                return Ok(None);
+            }
+
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            return match line.parse::<i64>() {
+                Ok(value) => Ok(Some(value)),
+                Err(_) => Err(format!("Invalid input: {}", line)),
+            };
+        }
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), String> {
+        writeln!(self.writer, "{}", value).map_err(|e| format!("Failed to write output: {}", e))
+    }
+}
+
+pub struct ClosureDevice<R, W>
+where
+    R: FnMut() -> Option<i64>,
+    W: FnMut(i64),
+{
+    read: R,
+    write: W,
+}
+
+impl<R, W> ClosureDevice<R, W>
+where
+    R: FnMut() -> Option<i64>,
+    W: FnMut(i64),
+{
+    pub fn new(read: R, write: W) -> Self {
+        Self { read, write }
+    }
+}
+
+impl<R, W> IoDevice for ClosureDevice<R, W>
+where
+    R: FnMut() -> Option<i64>,
+    W: FnMut(i64),
+{
+    fn read(&mut self) -> Result<Option<i64>, String> {
+        Ok((self.read)())
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), String> {
+        (self.write)(value);
+        Ok(())
+    }
+}
+
+// Blocks on the receiver for input, so a disconnected sender reads as end of input
+pub struct ChannelDevice {
+    receiver: Receiver<i64>,
+    sender: Sender<i64>,
+}
+
+impl ChannelDevice {
+    pub fn new(receiver: Receiver<i64>, sender: Sender<i64>) -> Self {
+        Self { receiver, sender }
+    }
+}
+
+impl IoDevice for ChannelDevice {
+    fn read(&mut self) -> Result<Option<i64>, String> {
+        Ok(self.receiver.recv().ok())
+    }
+
+    fn write(&mut self, value: i64) -> Result<(), String> {
+        self.sender
+            .send(value)
+            .map_err(|_| String::from("Output channel is disconnected"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn vec_device() {
+        let mut device = VecDevice::new(vec![1, 2]);
+        device.write(3).unwrap();
+
+        assert_eq!(Ok(Some(1)), device.read());
+        assert_eq!(Ok(Some(2)), device.read());
+        assert_eq!(Ok(None), device.read());
+        assert_eq!(&vec![3], device.outputs());
+    }
+
+    #[test]
+    fn stdio_device() {
+        let mut device = StdioDevice::new("12\n\n-4\n".as_bytes(), Vec::new());
+        device.write(7).unwrap();
+        device.write(-8).unwrap();
+
+        assert_eq!(Ok(Some(12)), device.read());
+        assert_eq!(Ok(Some(-4)), device.read());
+        assert_eq!(Ok(None), device.read());
+
+        let (_, written) = device.into_inner();
+        assert_eq!("7\n-8\n", String::from_utf8(written).unwrap());
+    }
+
+    #[test]
+    fn stdio_device_invalid_input_should_fail() {
+        let mut device = StdioDevice::new("abc\n".as_bytes(), Vec::new());
+
+        assert!(device.read().is_err());
+    }
+
+    #[test]
+    fn closure_device() {
+        let mut written = Vec::new();
+        {
+            let mut next = 0;
+            let mut device = ClosureDevice::new(
+                || {
+                    next += 1;
+                    Some(next)
+                },
+                |v| written.push(v),
+            );
+
+            assert_eq!(Ok(Some(1)), device.read());
+            assert_eq!(Ok(Some(2)), device.read());
+            device.write(5).unwrap();
+        }
+
+        assert_eq!(vec![5], written);
+    }
+
+    #[test]
+    fn channel_device() {
+        let (input_sender, input_receiver) = mpsc::channel();
+        let (output_sender, output_receiver) = mpsc::channel();
+        let mut device = ChannelDevice::new(input_receiver, output_sender);
+
+        input_sender.send(9).unwrap();
+        drop(input_sender);
+        device.write(10).unwrap();
+
+        assert_eq!(Ok(Some(9)), device.read());
+        assert_eq!(Ok(None), device.read());
+        assert_eq!(Ok(10), output_receiver.recv());
+
+        drop(output_receiver);
+        assert!(device.write(11).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+
+use crate::snapshot::Snapshot;
+
+// Steps kept in full, so they can be undone one at a time
+const DEFAULT_CAPACITY: usize = 100_000;
+
+// Steps between checkpoints, and how many are kept
+const DEFAULT_INTERVAL: u64 = 10_000;
+const DEFAULT_CHECKPOINTS: usize = 100;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub step: u64,
+    pub position: usize,
+    pub relative_base: i64,
+    pub last_output: Option<i64>,
+    // Cells the instruction wrote, with the values they held before
+    pub writes: Vec<(usize, i64)>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Io {
+    Input(i64),
+    // Which of the machine's queued outputs it was, or `None` if it went to a device
+    Output(Option<u64>),
+}
+
+// What a machine did, so it can go back to an earlier step.
+//
+// The latest steps are kept in full and undone one by one. Older ones are only reachable through
+// checkpoints, snapshots taken every `interval` steps that are replayed up to the step wanted,
+// which is why every input and output is kept back to the oldest checkpoint.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    capacity: usize,
+    interval: u64,
+    max_checkpoints: usize,
+    entries: VecDeque<Entry>,
+    pending: Option<Entry>,
+    checkpoints: VecDeque<Snapshot>,
+    io: VecDeque<(u64, Io)>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Journal::new(DEFAULT_CAPACITY, DEFAULT_INTERVAL, DEFAULT_CHECKPOINTS)
+    }
+}
+
+impl Journal {
+    pub fn new(capacity: usize, interval: u64, checkpoints: usize) -> Self {
+        Journal {
+            capacity,
+            interval: interval.max(1),
+            max_checkpoints: checkpoints,
+            entries: VecDeque::new(),
+            pending: None,
+            checkpoints: VecDeque::new(),
+            io: VecDeque::new(),
+        }
+    }
+
+    // The earliest step the machine can still go back to
+    pub fn oldest_step(&self) -> Option<u64> {
+        let entry = self.entries.front().map(|e| e.step);
+        let checkpoint = self.checkpoints.front().map(|c| c.steps);
+
+        match (entry, checkpoint) {
+            (Some(entry), Some(checkpoint)) => Some(entry.min(checkpoint)),
+            (entry, checkpoint) => entry.or(checkpoint),
+        }
+    }
+
+    // Steps that can be undone without replaying from a checkpoint
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn needs_checkpoint(&self, step: u64) -> bool {
+        self.max_checkpoints > 0
+            && step.is_multiple_of(self.interval)
+            && self.checkpoints.back().is_none_or(|c| c.steps < step)
+    }
+
+    pub(crate) fn checkpoint(&mut self, snapshot: Snapshot) {
+        self.checkpoints.push_back(snapshot);
+        if self.checkpoints.len() > self.max_checkpoints {
+            self.checkpoints.pop_front();
+            self.forget_io();
+        }
+    }
+
+    pub(crate) fn begin(&mut self, entry: Entry) {
+        self.pending = Some(entry);
+    }
+
+    pub(crate) fn write(&mut self, address: usize, old_value: i64) {
+        if let Some(entry) = &mut self.pending {
+            entry.writes.push((address, old_value));
+        }
+    }
+
+    pub(crate) fn record(&mut self, io: Io) {
+        if let Some(entry) = &self.pending {
+            self.io.push_back((entry.step, io));
+        }
+    }
+
+    pub(crate) fn commit(&mut self) {
+        if let Some(entry) = self.pending.take() {
+            self.entries.push_back(entry);
+        }
+
+        if self.entries.len() > self.capacity {
+            self.entries.pop_front();
+            self.forget_io();
+        }
+    }
+
+    // The instruction didn't complete, so nothing it did counts
+    pub(crate) fn discard(&mut self) {
+        if let Some(entry) = self.pending.take() {
+            while self.io.back().is_some_and(|(step, _)| *step == entry.step) {
+                self.io.pop_back();
+            }
+        }
+    }
+
+    // The latest step, with its inputs and outputs in the order they happened
+    pub(crate) fn pop(&mut self) -> Option<(Entry, Vec<Io>)> {
+        let entry = self.entries.pop_back()?;
+        let io = self.truncate(entry.step);
+
+        Some((entry, io))
+    }
+
+    // Step of the latest instruction that wrote to `address`
+    pub(crate) fn last_write(&self, address: usize) -> Option<u64> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.writes.iter().any(|(a, _)| *a == address))
+            .map(|entry| entry.step)
+    }
+
+    pub(crate) fn can_undo_to(&self, step: u64) -> bool {
+        self.entries.front().is_some_and(|e| e.step <= step)
+    }
+
+    pub(crate) fn checkpoint_before(&self, step: u64) -> Option<Snapshot> {
+        self.checkpoints
+            .iter()
+            .rev()
+            .find(|c| c.steps <= step)
+            .cloned()
+    }
+
+    // Forgets everything from `step` on, returning the inputs and outputs that happened then
+    pub(crate) fn truncate(&mut self, step: u64) -> Vec<Io> {
+        while self.entries.back().is_some_and(|e| e.step >= step) {
+            self.entries.pop_back();
+        }
+
+        while self.checkpoints.back().is_some_and(|c| c.steps > step) {
+            self.checkpoints.pop_back();
+        }
+
+        let mut io = Vec::new();
+        while self.io.back().is_some_and(|(s, _)| *s >= step) {
+            io.push(self.io.pop_back().unwrap().1);
+        }
+
+        io.reverse();
+        io
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.pending = None;
+        self.checkpoints.clear();
+        self.io.clear();
+    }
+
+    // Inputs and outputs only matter as far back as the machine can go
+    fn forget_io(&mut self) {
+        if let Some(oldest) = self.oldest_step() {
+            while self.io.front().is_some_and(|(step, _)| *step < oldest) {
+                self.io.pop_front();
+            }
+        }
+    }
+}
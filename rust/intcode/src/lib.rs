@@ -0,0 +1,64 @@
+use std::num::ParseIntError;
+
+pub use crate::analysis::{analyze, Analysis, Block, Edge};
+pub use crate::asm::assemble;
+pub use crate::compiler::Engine;
+pub use crate::debugger::Debugger;
+pub use crate::disasm::{decode, disassemble, listing, Line, Parameter};
+pub use crate::error::IntcodeError;
+pub use crate::fuzz::{compare, day_opcodes, shrink, FuzzError, Fuzzer, Mismatch, Outcome};
+pub use crate::io::{ChannelDevice, ClosureDevice, IoDevice, StdioDevice, VecDevice};
+pub use crate::journal::Journal;
+pub use crate::limits::Limits;
+pub use crate::machine::{Intcode, RunState};
+pub use crate::memory::DEFAULT_MEMORY_LIMIT;
+pub use crate::network::{Network, NetworkState};
+pub use crate::operation::{Opcode, ParameterMode};
+pub use crate::profiler::Profile;
+pub use crate::program::Program;
+pub use crate::snapshot::Snapshot;
+pub use crate::tracer::{JsonTracer, PrettyTracer, TraceEvent, Tracer};
+
+mod analysis;
+mod asm;
+mod compiler;
+mod debugger;
+mod disasm;
+mod error;
+mod fuzz;
+mod io;
+mod journal;
+mod limits;
+mod machine;
+mod memory;
+mod network;
+mod operation;
+mod profiler;
+mod program;
+#[cfg(test)]
+mod properties;
+mod snapshot;
+mod tracer;
+
+pub fn parse_program(contents: &str) -> Result<Vec<i64>, ParseIntError> {
+    contents
+        .trim()
+        .split(',')
+        .map(|s| s.trim().parse::<i64>())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_program_test() {
+        assert_eq!(Ok(vec![1, -2, 3, 4, 5]), parse_program("1,-2,3,4, 5\n"));
+    }
+
+    #[test]
+    fn parse_program_invalid_value_should_fail() {
+        assert!(parse_program("1,x,3").is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use crate::memory::Memory;
+
+// Guards against programs that never halt. All off by default.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Limits {
+    // Instructions a machine may execute over its whole life
+    pub steps: Option<u64>,
+    // How long a single call to `run`, `compute` or `run_with` may take
+    pub time: Option<Duration>,
+    // Fail once the machine gets back to a state it was already in without doing any I/O
+    pub detect_loops: bool,
+}
+
+// The position, relative base and a hash of memory
+pub(crate) type State = (usize, i64, u64);
+
+// Brent's cycle detection: remembers one state and compares every following state to it,
+// moving it forward at doubling intervals. Without I/O a machine is deterministic, so seeing a
+// state twice means it will loop forever, and the loop is found within a few times its length.
+//
+// States are compared by their memory hash, and memory is only compared in full when the hashes
+// match, so a collision can't stop a program that is still making progress.
+#[derive(Debug, Clone)]
+pub(crate) struct LoopDetector {
+    saved: Option<(State, Memory)>,
+    power: u64,
+    length: u64,
+}
+
+impl LoopDetector {
+    pub fn new() -> Self {
+        Self {
+            saved: None,
+            power: 1,
+            length: 0,
+        }
+    }
+
+    // Returns whether the state has been seen before
+    pub fn observe(&mut self, state: State, memory: &Memory) -> bool {
+        if let Some((saved, saved_memory)) = &self.saved {
+            if *saved == state && saved_memory.same_contents(memory) {
+                return true;
+            }
+        }
+
+        self.length += 1;
+        if self.saved.is_none() || self.length >= self.power {
+            self.saved = Some((state, memory.clone()));
+            self.power *= 2;
+            self.length = 0;
+        }
+
+        false
+    }
+
+    // I/O can change what the program does next, so states from before it don't count
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_cycles_after_a_tail() {
+        let mut detector = LoopDetector::new();
+        let states = (0..10).chain((10..17).cycle()).map(|i| (i, 0, 0));
+
+        let memory = Memory::new(&[]);
+
+        let seen = states.take(1000).position(|s| detector.observe(s, &memory));
+
+        assert!(seen.is_some());
+        assert!(seen.unwrap() < 10 + 4 * 7);
+    }
+
+    #[test]
+    fn distinct_states_are_not_a_cycle() {
+        let mut detector = LoopDetector::new();
+        let memory = Memory::new(&[]);
+
+        assert!((0..10000).all(|i| !detector.observe((0, 0, i), &memory)));
+    }
+
+    #[test]
+    fn hash_collisions_are_not_a_cycle() {
+        let mut detector = LoopDetector::new();
+
+        // Same position, relative base and hash, but different memory
+        let states = (0..100).map(|i| ((0, 0, 0), Memory::new(&[i])));
+
+        assert!(states
+            .into_iter()
+            .all(|(state, memory)| !detector.observe(state, &memory)));
+    }
+}
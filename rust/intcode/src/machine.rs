@@ -0,0 +1,1841 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::compiler::{Compiled, Engine, Op, Operand};
+use crate::error::IntcodeError;
+use crate::io::{IoDevice, Queues};
+use crate::journal::{Entry, Io, Journal};
+use crate::limits::{Limits, LoopDetector};
+use crate::memory::Memory;
+use crate::operation::{parse_operation, Opcode, ParameterMode};
+use crate::profiler::Profile;
+use crate::snapshot::Snapshot;
+use crate::tracer::{TraceEvent, Tracer};
+
+type Decoded = (Opcode, ParameterMode, ParameterMode, ParameterMode);
+
+// Reading the clock on every instruction would cost more than the instruction itself
+const CLOCK_INTERVAL: u64 = 1 << 12;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RunState {
+    Halted,
+    NeedsInput,
+    Output(i64),
+}
+
+pub struct Intcode {
+    halted: bool,
+    initial_program: Vec<i64>,
+    memory: Memory,
+    current_position: usize,
+    relative_base: i64,
+    inputs: VecDeque<i64>,
+    outputs: VecDeque<i64>,
+    // Outputs ever put on `outputs`, so undoing one takes off exactly that one
+    queued_outputs: u64,
+    // Set while instructions write to `outputs` instead of to a device
+    queueing: bool,
+    last_output: Option<i64>,
+    tracer: Option<Box<dyn Tracer + Send>>,
+    profile: Option<Profile>,
+    steps: u64,
+    step_limit: Option<u64>,
+    time_limit: Option<Duration>,
+    loop_detector: Option<LoopDetector>,
+    // Decoded instructions by address, `None` when caching is turned off
+    decode_cache: Option<Vec<Option<Decoded>>>,
+    // Only there with the compiled engine
+    compiled: Option<Compiled>,
+    journal: Option<Journal>,
+}
+
+impl fmt::Debug for Intcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Intcode")
+            .field("halted", &self.halted)
+            .field("current_position", &self.current_position)
+            .field("relative_base", &self.relative_base)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .field("last_output", &self.last_output)
+            .field("tracing", &self.tracer.is_some())
+            .field("steps", &self.steps)
+            .finish()
+    }
+}
+
+impl Intcode {
+    // Starts on the interpreter, without limits; see `set_engine` and `set_limits`
+    pub fn new(program: &[i64]) -> Self {
+        Self {
+            halted: false,
+            initial_program: program.to_vec(),
+            memory: Memory::new(program),
+            current_position: 0,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+            queued_outputs: 0,
+            queueing: false,
+            last_output: None,
+            tracer: None,
+            profile: None,
+            steps: 0,
+            step_limit: None,
+            time_limit: None,
+            loop_detector: None,
+            decode_cache: Some(Vec::new()),
+            compiled: None,
+            journal: None,
+        }
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    // Number of instructions executed so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn set_step_limit(&mut self, limit: Option<u64>) {
+        self.step_limit = limit;
+    }
+
+    pub fn set_time_limit(&mut self, limit: Option<Duration>) {
+        self.time_limit = limit;
+    }
+
+    pub fn set_loop_detection(&mut self, enabled: bool) {
+        self.loop_detector = if enabled {
+            Some(LoopDetector::new())
+        } else {
+            None
+        };
+    }
+
+    pub fn limits(&self) -> Limits {
+        Limits {
+            steps: self.step_limit,
+            time: self.time_limit,
+            detect_loops: self.loop_detector.is_some(),
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.set_step_limit(limits.steps);
+        self.set_time_limit(limits.time);
+        self.set_loop_detection(limits.detect_loops);
+    }
+
+    // Tracing and profiling always use the interpreter, whichever engine is set
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.compiled = match engine {
+            Engine::Interpreter => None,
+            Engine::Compiled => Some(Compiled::default()),
+        };
+    }
+
+    pub fn engine(&self) -> Engine {
+        match self.compiled {
+            Some(_) => Engine::Compiled,
+            None => Engine::Interpreter,
+        }
+    }
+
+    // On by default; turning it off decodes every instruction from scratch
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache = if enabled { Some(Vec::new()) } else { None };
+    }
+
+    pub fn push_input(&mut self, input: i64) {
+        self.inputs.push_back(input);
+    }
+
+    pub fn push_inputs<I: IntoIterator<Item = i64>>(&mut self, inputs: I) {
+        self.inputs.extend(inputs);
+    }
+
+    pub fn pending_inputs(&self) -> &VecDeque<i64> {
+        &self.inputs
+    }
+
+    // Outputs queued by `run` that haven't been taken yet
+    pub fn outputs(&self) -> &VecDeque<i64> {
+        &self.outputs
+    }
+
+    pub fn pop_output(&mut self) -> Option<i64> {
+        self.outputs.pop_front()
+    }
+
+    pub fn drain_outputs(&mut self) -> Vec<i64> {
+        self.outputs.drain(..).collect()
+    }
+
+    pub fn last_output(&self) -> Option<i64> {
+        self.last_output
+    }
+
+    pub fn initial_program(&self) -> &Vec<i64> {
+        &self.initial_program
+    }
+
+    // The program image and whatever was written right after it; see `memory` for any address
+    pub fn current_state(&self) -> &[i64] {
+        self.memory.dense()
+    }
+
+    pub fn position(&self) -> usize {
+        self.current_position
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    // Memory that was never written reads as zero
+    pub fn memory(&self, address: usize) -> i64 {
+        self.memory.get(address)
+    }
+
+    // Going back past a change made from outside wouldn't undo it, so it also clears the journal
+    pub fn set_memory(&mut self, address: usize, value: i64) -> Result<(), IntcodeError> {
+        self.memory.set(address, value)?;
+        self.invalidate(address);
+        self.forget_states();
+        self.clear_journal();
+
+        Ok(())
+    }
+
+    // Number of memory cells allocated so far, which is what the memory limit applies to
+    pub fn allocated_memory(&self) -> usize {
+        self.memory.allocated()
+    }
+
+    // Defaults to `DEFAULT_MEMORY_LIMIT` cells; `None` lifts the limit
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory.set_limit(limit);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            memory: self.memory.dense().to_vec(),
+            sparse: self.memory.sparse(),
+            position: self.current_position,
+            relative_base: self.relative_base,
+            inputs: self.inputs.iter().copied().collect(),
+            outputs: self.outputs.iter().copied().collect(),
+            last_output: self.last_output,
+            halted: self.halted,
+            steps: self.steps,
+        }
+    }
+
+    // The tracer and limits are kept, everything else comes from the snapshot
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), IntcodeError> {
+        self.clear_decode_cache();
+        self.memory.reset(&snapshot.memory);
+        for (address, value) in &snapshot.sparse {
+            self.memory.set(*address, *value)?;
+        }
+
+        self.current_position = snapshot.position;
+        self.relative_base = snapshot.relative_base;
+        self.inputs = snapshot.inputs.iter().copied().collect();
+        self.outputs = snapshot.outputs.iter().copied().collect();
+        self.last_output = snapshot.last_output;
+        self.halted = snapshot.halted;
+        self.steps = snapshot.steps;
+        self.forget_states();
+        self.clear_journal();
+
+        Ok(())
+    }
+
+    // A new machine in the same state, without a tracer
+    pub fn fork(&self) -> Self {
+        let mut intcode = Intcode::new(&self.initial_program);
+        intcode.set_limits(self.limits());
+        intcode.compiled = self.compiled.clone();
+        intcode.decode_cache = self.decode_cache.clone();
+        intcode.memory = self.memory.clone();
+        intcode.current_position = self.current_position;
+        intcode.relative_base = self.relative_base;
+        intcode.inputs = self.inputs.clone();
+        intcode.outputs = self.outputs.clone();
+        intcode.queued_outputs = self.queued_outputs;
+        intcode.last_output = self.last_output;
+        intcode.halted = self.halted;
+        intcode.steps = self.steps;
+        intcode
+    }
+
+    // Back to `initial_program` with empty queues, reusing the memory already allocated
+    pub fn reset(&mut self) {
+        self.clear_decode_cache();
+        self.memory.reset(&self.initial_program);
+        self.current_position = 0;
+        self.relative_base = 0;
+        self.inputs.clear();
+        self.outputs.clear();
+        self.last_output = None;
+        self.halted = false;
+        self.steps = 0;
+        self.forget_states();
+        self.clear_journal();
+
+        if self.profile.is_some() {
+            self.profile = Some(Profile::default());
+        }
+    }
+
+    // Tracing is off by default; the tracer sees every instruction once it has executed
+    pub fn set_tracer<T: Tracer + Send + 'static>(&mut self, tracer: T) {
+        self.tracer = Some(Box::new(tracer));
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Box<dyn Tracer + Send>> {
+        self.tracer.take()
+    }
+
+    // Profiling is off by default; turning it on starts a new profile
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = if enabled {
+            Some(Profile::default())
+        } else {
+            None
+        };
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.take()
+    }
+
+    // Journaling is off by default; with it the machine can go back to earlier steps
+    pub fn set_journal(&mut self, journal: Option<Journal>) {
+        self.journal = journal;
+    }
+
+    pub fn journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    // Undoes the last instruction executed
+    pub fn step_back(&mut self) -> Result<(), IntcodeError> {
+        let step = self.steps.saturating_sub(1);
+        match self.journal.as_mut().and_then(|journal| journal.pop()) {
+            Some((entry, io)) => self.undo(entry, io),
+            None => Err(IntcodeError::HistoryUnavailable { step }),
+        }
+    }
+
+    // Goes back to just before the latest instruction that wrote to `address`, returning its
+    // step, or `None` if the journal doesn't hold one
+    pub fn rewind_to_write(&mut self, address: usize) -> Result<Option<u64>, IntcodeError> {
+        match self.journal.as_ref().and_then(|j| j.last_write(address)) {
+            Some(step) => self.goto_step(step).map(|_| Some(step)),
+            None => Ok(None),
+        }
+    }
+
+    // Goes back, or runs forward, until `step` instructions have been executed. Running forward
+    // stops early, returning the state, if the machine halts or needs input.
+    pub fn goto_step(&mut self, step: u64) -> Result<Option<RunState>, IntcodeError> {
+        if step < self.steps {
+            self.go_back(step)?;
+        }
+
+        while self.steps < step {
+            if let Some(state) = self.step()? {
+                return Ok(Some(state));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn go_back(&mut self, step: u64) -> Result<(), IntcodeError> {
+        let journal = match &mut self.journal {
+            Some(journal) => journal,
+            None => return Err(IntcodeError::HistoryUnavailable { step }),
+        };
+
+        if journal.can_undo_to(step) {
+            while self.steps > step {
+                self.step_back()?;
+            }
+
+            return Ok(());
+        }
+
+        let checkpoint = journal
+            .checkpoint_before(step)
+            .ok_or(IntcodeError::HistoryUnavailable { step })?;
+        let io = journal.truncate(checkpoint.steps);
+
+        // Restoring would forget the queues and the journal, which replaying needs
+        let journal = self.journal.take();
+        let inputs = mem::take(&mut self.inputs);
+        let outputs = mem::take(&mut self.outputs);
+        self.restore(&checkpoint)?;
+        self.journal = journal;
+        self.inputs = inputs;
+        self.outputs = outputs;
+        self.take_back(&io);
+
+        Ok(())
+    }
+
+    fn undo(&mut self, entry: Entry, io: Vec<Io>) -> Result<(), IntcodeError> {
+        for (address, value) in entry.writes.into_iter().rev() {
+            self.memory.set(address, value)?;
+            self.invalidate(address);
+        }
+
+        self.current_position = entry.position;
+        self.relative_base = entry.relative_base;
+        self.last_output = entry.last_output;
+        self.halted = false;
+        self.steps = entry.step;
+        self.take_back(&io);
+        self.forget_states();
+
+        Ok(())
+    }
+
+    // Inputs go back in the queue to be read again. The queue holds the latest outputs queued,
+    // so an output taken back is at its end unless it was already taken from the front.
+    fn take_back(&mut self, io: &[Io]) {
+        for io in io.iter().rev() {
+            match *io {
+                Io::Input(value) => self.inputs.push_front(value),
+                Io::Output(Some(sequence)) => {
+                    let later = (self.queued_outputs - sequence) as usize;
+                    let kept = self.outputs.len().saturating_sub(later);
+                    self.outputs.truncate(kept);
+                    self.queued_outputs = sequence;
+                }
+                Io::Output(None) => {}
+            }
+        }
+    }
+
+    fn clear_journal(&mut self) {
+        if let Some(journal) = &mut self.journal {
+            journal.clear();
+        }
+    }
+
+    fn trace(&mut self, event: TraceEvent) -> Result<(), IntcodeError> {
+        match &mut self.tracer {
+            Some(tracer) => tracer.trace(&event).map_err(IntcodeError::Tracer),
+            None => Ok(()),
+        }
+    }
+
+    fn trace_instruction(
+        &mut self,
+        opcode: Opcode,
+        modes: [ParameterMode; 3],
+        operands: &[i64],
+    ) -> Result<(), IntcodeError> {
+        // Called once for every instruction that executes, which is also what the profile counts
+        if let Some(profile) = &mut self.profile {
+            profile.instruction(self.current_position, opcode, modes);
+        }
+
+        if self.tracer.is_none() {
+            return Ok(());
+        }
+
+        self.trace(TraceEvent::Instruction {
+            ip: self.current_position,
+            opcode,
+            modes: modes[..operands.len()].to_vec(),
+            operands: operands.to_vec(),
+        })
+    }
+
+    fn get(&self, index: usize) -> i64 {
+        self.memory.get(index)
+    }
+
+    fn set(&mut self, index: usize, value: i64) -> Result<(), IntcodeError> {
+        if let Some(journal) = &mut self.journal {
+            journal.write(index, self.memory.get(index));
+        }
+
+        self.memory.set(index, value)?;
+
+        // From here on the compiled code can't be trusted, so the interpreter takes over
+        if let Some(compiled) = &mut self.compiled {
+            if compiled.is_code(index) {
+                compiled.mark_self_modified();
+            }
+        }
+
+        self.invalidate(index);
+
+        if let Some(profile) = &mut self.profile {
+            profile.write(index, value);
+        }
+
+        if self.tracer.is_some() {
+            self.trace(TraceEvent::Write {
+                address: index,
+                value,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    // Self-modifying programs overwrite instructions, which must then be decoded again.
+    // Writes from outside to compiled code just mean compiling it again.
+    fn invalidate(&mut self, address: usize) {
+        if let Some(compiled) = &mut self.compiled {
+            if compiled.is_code(address) && !compiled.self_modified() {
+                compiled.clear();
+            }
+        }
+
+        if let Some(entry) = self
+            .decode_cache
+            .as_mut()
+            .and_then(|cache| cache.get_mut(address))
+        {
+            *entry = None;
+        }
+    }
+
+    fn forget_states(&mut self) {
+        if let Some(detector) = &mut self.loop_detector {
+            detector.reset();
+        }
+    }
+
+    fn clear_decode_cache(&mut self) {
+        if let Some(cache) = &mut self.decode_cache {
+            cache.clear();
+        }
+
+        if let Some(compiled) = &mut self.compiled {
+            compiled.clear();
+        }
+    }
+
+    fn decode(&mut self) -> Result<Decoded, IntcodeError> {
+        let position = self.current_position;
+
+        if let Some(Some(decoded)) = self.decode_cache.as_ref().and_then(|c| c.get(position)) {
+            return Ok(*decoded);
+        }
+
+        let decoded = parse_operation(position, self.get(position))?;
+
+        // Only the dense region holds code in practice, so don't grow the cache past it
+        if let Some(cache) = &mut self.decode_cache {
+            if position < self.memory.dense().len() {
+                if position >= cache.len() {
+                    cache.resize(self.memory.dense().len(), None);
+                }
+
+                cache[position] = Some(decoded);
+            }
+        }
+
+        Ok(decoded)
+    }
+
+    fn address(&self, address: i64) -> Result<usize, IntcodeError> {
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                ip: self.current_position,
+                address,
+            });
+        }
+
+        Ok(address as usize)
+    }
+
+    fn relative(&self, offset: i64) -> Result<i64, IntcodeError> {
+        self.checked(self.relative_base.checked_add(offset))
+    }
+
+    fn checked(&self, value: Option<i64>) -> Result<i64, IntcodeError> {
+        value.ok_or(IntcodeError::Overflow {
+            ip: self.current_position,
+        })
+    }
+
+    fn get_parameter(&mut self, offset: usize, mode: ParameterMode) -> Result<i64, IntcodeError> {
+        let index = self.current_position + offset;
+        let index = match mode {
+            ParameterMode::Position => {
+                let address = self.get(index);
+                self.address(address)?
+            }
+            ParameterMode::Relative => {
+                let address = self.relative(self.get(index))?;
+                self.address(address)?
+            }
+            ParameterMode::Immediate => return Ok(self.get(index)),
+        };
+
+        if let Some(profile) = &mut self.profile {
+            profile.read(index);
+        }
+
+        Ok(self.get(index))
+    }
+
+    fn get_result_index(
+        &mut self,
+        offset: usize,
+        mode: ParameterMode,
+    ) -> Result<usize, IntcodeError> {
+        let index = self.current_position + offset;
+        let address = match mode {
+            ParameterMode::Position => self.get(index),
+            ParameterMode::Relative => self.relative(self.get(index))?,
+            ParameterMode::Immediate => {
+                return Err(IntcodeError::WriteInImmediateMode {
+                    ip: self.current_position,
+                })
+            }
+        };
+
+        self.address(address)
+    }
+
+    // Runs until the program halts or blocks on input, queueing every output along the way
+    pub fn run(&mut self) -> Result<RunState, IntcodeError> {
+        self.with_queues(|intcode, queues| intcode.execute(queues, false))
+    }
+
+    // Runs until the next output, a read from an empty input queue or halt
+    pub fn compute(&mut self) -> Result<RunState, IntcodeError> {
+        self.with_queues(|intcode, queues| intcode.execute(queues, true))
+    }
+
+    // Like `run`, but running out of input is an error rather than a pause
+    pub fn run_to_halt(&mut self) -> Result<RunState, IntcodeError> {
+        match self.run()? {
+            RunState::NeedsInput => Err(IntcodeError::InputExhausted {
+                ip: self.current_position,
+            }),
+            state => Ok(state),
+        }
+    }
+
+    // Runs until the program halts or the device has no input, sending every output to the device
+    pub fn run_with<D: IoDevice + ?Sized>(
+        &mut self,
+        device: &mut D,
+    ) -> Result<RunState, IntcodeError> {
+        self.execute(device, false)
+    }
+
+    // Executes a single instruction, queueing its output; `None` means the machine can keep going
+    pub fn step(&mut self) -> Result<Option<RunState>, IntcodeError> {
+        if self.halted {
+            return Ok(Some(RunState::Halted));
+        }
+
+        match self.with_queues(|intcode, queues| intcode.execute_one(queues, false))? {
+            None if self.halted => Ok(Some(RunState::Halted)),
+            state => Ok(state),
+        }
+    }
+
+    fn with_queues<T, F>(&mut self, f: F) -> Result<T, IntcodeError>
+    where
+        F: FnOnce(&mut Self, &mut Queues) -> Result<T, IntcodeError>,
+    {
+        let mut queues = Queues {
+            inputs: mem::take(&mut self.inputs),
+            outputs: mem::take(&mut self.outputs),
+        };
+
+        self.queueing = true;
+        let result = f(self, &mut queues);
+        self.queueing = false;
+
+        self.inputs = queues.inputs;
+        self.outputs = queues.outputs;
+
+        result
+    }
+
+    fn execute<D: IoDevice + ?Sized>(
+        &mut self,
+        device: &mut D,
+        stop_on_output: bool,
+    ) -> Result<RunState, IntcodeError> {
+        let deadline = self.time_limit.map(|limit| (Instant::now() + limit, limit));
+
+        while !self.halted {
+            let state = if self.use_compiled() {
+                self.execute_compiled(device, stop_on_output)?
+            } else {
+                self.execute_one(device, stop_on_output)?
+            };
+
+            if let Some(state) = state {
+                return Ok(state);
+            }
+
+            if let Some((deadline, limit)) = deadline {
+                if self.steps.is_multiple_of(CLOCK_INTERVAL) && Instant::now() >= deadline {
+                    return Err(IntcodeError::TimeLimitExceeded { limit });
+                }
+            }
+        }
+
+        Ok(RunState::Halted)
+    }
+
+    fn check_limits(&mut self) -> Result<(), IntcodeError> {
+        if let Some(limit) = self.step_limit {
+            if self.steps >= limit {
+                return Err(IntcodeError::StepLimitExceeded { limit });
+            }
+        }
+
+        if let Some(detector) = &mut self.loop_detector {
+            let state = (
+                self.current_position,
+                self.relative_base,
+                self.memory.hash(),
+            );
+
+            if detector.observe(state, &self.memory) {
+                return Err(IntcodeError::InfiniteLoop {
+                    ip: self.current_position,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn use_compiled(&self) -> bool {
+        match &self.compiled {
+            Some(compiled) => {
+                !compiled.self_modified()
+                    && self.tracer.is_none()
+                    && self.profile.is_none()
+                    && self.journal.is_none()
+            }
+            None => false,
+        }
+    }
+
+    fn operand(&self, operand: Operand) -> Result<i64, IntcodeError> {
+        match operand {
+            Operand::Immediate(value) => Ok(value),
+            Operand::Position(address) => Ok(self.get(address)),
+            Operand::Relative(offset) => Ok(self.get(self.address(self.relative(offset)?)?)),
+        }
+    }
+
+    fn result_address(&self, operand: Operand) -> Result<usize, IntcodeError> {
+        match operand {
+            Operand::Position(address) => Ok(address),
+            Operand::Relative(offset) => self.address(self.relative(offset)?),
+            Operand::Immediate(_) => Err(IntcodeError::WriteInImmediateMode {
+                ip: self.current_position,
+            }),
+        }
+    }
+
+    // Same as `execute_one`, minus decoding, tracing and profiling
+    fn execute_compiled<D: IoDevice + ?Sized>(
+        &mut self,
+        device: &mut D,
+        stop_on_output: bool,
+    ) -> Result<Option<RunState>, IntcodeError> {
+        let ip = self.current_position;
+        let memory = &self.memory;
+        let compiled = self
+            .compiled
+            .as_mut()
+            .expect("Compiled engine without code");
+
+        let op = match compiled.get(ip) {
+            Some(op) => op,
+            None if ip < memory.len() => match compiled.compile(ip, |a| memory.get(a)) {
+                Some(op) => op,
+                None => return self.execute_one(device, stop_on_output),
+            },
+            None => return self.execute_one(device, stop_on_output),
+        };
+
+        self.check_limits()?;
+
+        match op {
+            Op::Add(a, b, result) | Op::Multiply(a, b, result) => {
+                let (a, b) = (self.operand(a)?, self.operand(b)?);
+                let value = if let Op::Add(..) = op {
+                    a.checked_add(b)
+                } else {
+                    a.checked_mul(b)
+                };
+                let value = self.checked(value)?;
+                let address = self.result_address(result)?;
+                self.set(address, value)?;
+                self.current_position += 4;
+            }
+            Op::LessThan(a, b, result) | Op::Equals(a, b, result) => {
+                let (a, b) = (self.operand(a)?, self.operand(b)?);
+                let value = if let Op::LessThan(..) = op {
+                    a < b
+                } else {
+                    a == b
+                };
+                let address = self.result_address(result)?;
+                self.set(address, value as i64)?;
+                self.current_position += 4;
+            }
+            Op::Input(result) => {
+                let address = self.result_address(result)?;
+                self.forget_states();
+
+                match device.read().map_err(IntcodeError::Device)? {
+                    Some(value) => {
+                        self.set(address, value)?;
+                        self.current_position += 2;
+                    }
+                    None => return Ok(Some(RunState::NeedsInput)),
+                }
+            }
+            Op::Output(value) => {
+                let value = self.operand(value)?;
+                self.last_output = Some(value);
+                self.current_position += 2;
+                self.forget_states();
+
+                if stop_on_output {
+                    self.steps += 1;
+                    return Ok(Some(RunState::Output(value)));
+                }
+
+                device.write(value).map_err(IntcodeError::Device)?;
+            }
+            Op::JumpIfTrue(condition, target) | Op::JumpIfFalse(condition, target) => {
+                // Both are read even when the jump isn't taken, as the interpreter does
+                let condition = self.operand(condition)? != 0;
+                let target = self.operand(target)?;
+                let jump = if let Op::JumpIfTrue(..) = op {
+                    condition
+                } else {
+                    !condition
+                };
+
+                self.current_position = if jump {
+                    self.address(target)?
+                } else {
+                    self.current_position + 3
+                };
+            }
+            Op::AdjustRelativeBase(adjustment) => {
+                let adjustment = self.operand(adjustment)?;
+                self.relative_base = self.checked(self.relative_base.checked_add(adjustment))?;
+                self.current_position += 2;
+            }
+            Op::Halt => self.halted = true,
+        }
+
+        self.steps += 1;
+        Ok(None)
+    }
+
+    // Executes the instruction at the current position, returning the state if the machine paused
+    fn execute_one<D: IoDevice + ?Sized>(
+        &mut self,
+        device: &mut D,
+        stop_on_output: bool,
+    ) -> Result<Option<RunState>, IntcodeError> {
+        if self.journal.is_none() {
+            return self.execute_instruction(device, stop_on_output);
+        }
+
+        self.begin_entry();
+        let steps = self.steps;
+        let result = self.execute_instruction(device, stop_on_output);
+
+        if let Some(journal) = &mut self.journal {
+            if result.is_ok() && self.steps > steps {
+                journal.commit();
+            } else {
+                journal.discard();
+            }
+        }
+
+        result
+    }
+
+    fn begin_entry(&mut self) {
+        let checkpoint = match &self.journal {
+            Some(journal) if journal.needs_checkpoint(self.steps) => Some(self.snapshot()),
+            _ => None,
+        };
+
+        if let Some(journal) = &mut self.journal {
+            if let Some(checkpoint) = checkpoint {
+                journal.checkpoint(checkpoint);
+            }
+
+            journal.begin(Entry {
+                step: self.steps,
+                position: self.current_position,
+                relative_base: self.relative_base,
+                last_output: self.last_output,
+                writes: Vec::new(),
+            });
+        }
+    }
+
+    fn execute_instruction<D: IoDevice + ?Sized>(
+        &mut self,
+        device: &mut D,
+        stop_on_output: bool,
+    ) -> Result<Option<RunState>, IntcodeError> {
+        if self.current_position >= self.memory.len() {
+            self.halted = true;
+            return Ok(Some(RunState::Halted));
+        }
+
+        self.check_limits()?;
+
+        let (opcode, first_mode, second_mode, third_mode) = self.decode()?;
+        let modes = [first_mode, second_mode, third_mode];
+
+        match opcode {
+            Opcode::Add => {
+                let first_parameter = self.get_parameter(1, first_mode)?;
+                let second_parameter = self.get_parameter(2, second_mode)?;
+                let result_index = self.get_result_index(3, third_mode)?;
+                let operands = [first_parameter, second_parameter, result_index as i64];
+                self.trace_instruction(opcode, modes, &operands)?;
+
+                let sum = self.checked(first_parameter.checked_add(second_parameter))?;
+                self.set(result_index, sum)?;
+
+                self.current_position += 4;
+            }
+            Opcode::Multiply => {
+                let first_parameter = self.get_parameter(1, first_mode)?;
+                let second_parameter = self.get_parameter(2, second_mode)?;
+                let result_index = self.get_result_index(3, third_mode)?;
+                let operands = [first_parameter, second_parameter, result_index as i64];
+                self.trace_instruction(opcode, modes, &operands)?;
+
+                let product = self.checked(first_parameter.checked_mul(second_parameter))?;
+                self.set(result_index, product)?;
+
+                self.current_position += 4;
+            }
+            Opcode::Set => {
+                let result_index = self.get_result_index(1, first_mode)?;
+
+                self.forget_states();
+
+                match device.read().map_err(IntcodeError::Device)? {
+                    Some(i) => {
+                        if let Some(journal) = &mut self.journal {
+                            journal.record(Io::Input(i));
+                        }
+
+                        self.trace_instruction(opcode, modes, &[result_index as i64])?;
+                        self.set(result_index, i)?;
+                        self.current_position += 2;
+                    }
+                    None => {
+                        return Ok(Some(RunState::NeedsInput));
+                    }
+                }
+            }
+            Opcode::Output => {
+                let value = self.get_parameter(1, first_mode)?;
+                self.trace_instruction(opcode, modes, &[value])?;
+
+                self.last_output = Some(value);
+                self.current_position += 2;
+                self.forget_states();
+
+                if stop_on_output {
+                    if let Some(journal) = &mut self.journal {
+                        journal.record(Io::Output(None));
+                    }
+
+                    self.steps += 1;
+                    return Ok(Some(RunState::Output(value)));
+                }
+
+                device.write(value).map_err(IntcodeError::Device)?;
+
+                let sequence = if self.queueing {
+                    self.queued_outputs += 1;
+                    Some(self.queued_outputs - 1)
+                } else {
+                    None
+                };
+                if let Some(journal) = &mut self.journal {
+                    journal.record(Io::Output(sequence));
+                }
+            }
+            Opcode::JumpIfTrue => {
+                let first_parameter = self.get_parameter(1, first_mode)?;
+                let second_parameter = self.get_parameter(2, second_mode)?;
+                self.trace_instruction(opcode, modes, &[first_parameter, second_parameter])?;
+
+                if first_parameter != 0 {
+                    self.current_position = self.address(second_parameter)?;
+                } else {
+                    self.current_position += 3;
+                }
+            }
+            Opcode::JumpIfFalse => {
+                let first_parameter = self.get_parameter(1, first_mode)?;
+                let second_parameter = self.get_parameter(2, second_mode)?;
+                self.trace_instruction(opcode, modes, &[first_parameter, second_parameter])?;
+
+                if first_parameter == 0 {
+                    self.current_position = self.address(second_parameter)?;
+                } else {
+                    self.current_position += 3;
+                }
+            }
+            Opcode::LessThan => {
+                let first_parameter = self.get_parameter(1, first_mode)?;
+                let second_parameter = self.get_parameter(2, second_mode)?;
+                let result_index = self.get_result_index(3, third_mode)?;
+                let operands = [first_parameter, second_parameter, result_index as i64];
+                self.trace_instruction(opcode, modes, &operands)?;
+
+                let value = if first_parameter < second_parameter {
+                    1
+                } else {
+                    0
+                };
+
+                self.set(result_index, value)?;
+
+                self.current_position += 4;
+            }
+            Opcode::Equals => {
+                let first_parameter = self.get_parameter(1, first_mode)?;
+                let second_parameter = self.get_parameter(2, second_mode)?;
+                let result_index = self.get_result_index(3, third_mode)?;
+                let operands = [first_parameter, second_parameter, result_index as i64];
+                self.trace_instruction(opcode, modes, &operands)?;
+
+                let value = if first_parameter == second_parameter {
+                    1
+                } else {
+                    0
+                };
+
+                self.set(result_index, value)?;
+
+                self.current_position += 4;
+            }
+            Opcode::SetRelativeBase => {
+                let base_adjustment = self.get_parameter(1, first_mode)?;
+                self.trace_instruction(opcode, modes, &[base_adjustment])?;
+
+                let from = self.relative_base;
+                self.relative_base =
+                    self.checked(self.relative_base.checked_add(base_adjustment))?;
+
+                if let Some(profile) = &mut self.profile {
+                    profile.relative_base(self.relative_base);
+                }
+
+                if self.tracer.is_some() {
+                    self.trace(TraceEvent::RelativeBase {
+                        from,
+                        to: self.relative_base,
+                    })?;
+                }
+
+                self.current_position += 2;
+            }
+            Opcode::Halt => {
+                self.trace_instruction(opcode, modes, &[])?;
+                self.halted = true;
+            }
+        };
+
+        self.steps += 1;
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{ChannelDevice, ClosureDevice, VecDevice};
+    use crate::journal::Journal;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+
+    #[test]
+    fn compute_test_1() {
+        let mut intcode = Intcode::new(&[1101, 100, -1, 4, 0]);
+        intcode.push_input(1);
+        intcode.compute().unwrap();
+
+        let expected = vec![1101, 100, -1, 4, 99];
+
+        assert_eq!(&expected, intcode.current_state());
+    }
+
+    #[test]
+    fn compute_test_2() {
+        let mut intcode = Intcode::new(&[1, 0, 0, 0, 99]);
+        intcode.push_input(1);
+        intcode.compute().unwrap();
+
+        let expected = vec![2, 0, 0, 0, 99];
+
+        assert_eq!(&expected, intcode.current_state());
+    }
+
+    #[test]
+    fn compute_test_3() {
+        let mut intcode = Intcode::new(&[2, 3, 0, 3, 99]);
+        intcode.push_input(1);
+        intcode.compute().unwrap();
+
+        let expected = vec![2, 3, 0, 6, 99];
+
+        assert_eq!(&expected, intcode.current_state());
+    }
+
+    #[test]
+    fn compute_test_4() {
+        let mut intcode = Intcode::new(&[2, 4, 4, 5, 99, 0]);
+        intcode.push_input(1);
+        intcode.compute().unwrap();
+
+        let expected = vec![2, 4, 4, 5, 99, 9801];
+
+        assert_eq!(&expected, intcode.current_state());
+    }
+
+    #[test]
+    fn compute_test_5() {
+        let mut intcode = Intcode::new(&[1, 1, 1, 4, 99, 5, 6, 0, 99]);
+        intcode.push_input(1);
+        intcode.compute().unwrap();
+
+        let expected = vec![30, 1, 1, 4, 2, 5, 6, 0, 99];
+
+        assert_eq!(&expected, intcode.current_state());
+    }
+
+    #[test]
+    fn compute_test_6() {
+        let mut intcode = Intcode::new(&[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        intcode.push_input(1);
+        intcode.compute().unwrap();
+
+        let expected = vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50];
+
+        assert_eq!(&expected, intcode.current_state());
+    }
+
+    #[test]
+    fn compute_test_7() {
+        let mut intcode = Intcode::new(&[1002, 4, 3, 4, 33]);
+        intcode.push_input(1);
+        intcode.compute().unwrap();
+
+        let expected = vec![1002, 4, 3, 4, 99];
+
+        assert_eq!(&expected, intcode.current_state());
+    }
+
+    #[test]
+    fn relative_mode_test_1() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut intcode = Intcode::new(&program);
+        intcode.run().unwrap();
+
+        assert_eq!(program, intcode.drain_outputs());
+    }
+
+    #[test]
+    fn relative_mode_test_2() {
+        let program = vec![104i64, 1125899906842624, 99];
+        let mut intcode = Intcode::new(&program);
+        intcode.run().unwrap();
+
+        assert_eq!(1125899906842624, intcode.last_output().unwrap());
+    }
+
+    #[test]
+    fn relative_mode_test_3() {
+        let program = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
+        let mut intcode = Intcode::new(&program);
+        intcode.run().unwrap();
+
+        assert_eq!(16, intcode.last_output().unwrap().to_string().len());
+    }
+
+    #[test]
+    fn compute_returns_each_event() {
+        let mut intcode = Intcode::new(&[3, 9, 4, 9, 4, 9, 99, 0, 0, 0]);
+
+        assert_eq!(Ok(RunState::NeedsInput), intcode.compute());
+        assert_eq!(Ok(RunState::NeedsInput), intcode.compute());
+
+        intcode.push_input(42);
+
+        assert_eq!(Ok(RunState::Output(42)), intcode.compute());
+        assert_eq!(Ok(RunState::Output(42)), intcode.compute());
+        assert_eq!(Ok(RunState::Halted), intcode.compute());
+        assert_eq!(Ok(RunState::Halted), intcode.compute());
+        assert!(intcode.outputs().is_empty());
+    }
+
+    #[test]
+    fn run_queues_outputs_until_input_is_needed() {
+        // Echoes every input doubled, forever
+        let mut intcode = Intcode::new(&[3, 9, 1002, 9, 2, 9, 4, 9, 1105, 1, 0]);
+        intcode.push_inputs(vec![1, 2, 3]);
+
+        assert_eq!(Ok(RunState::NeedsInput), intcode.run());
+        assert_eq!(Some(2), intcode.pop_output());
+        assert_eq!(vec![4, 6], intcode.drain_outputs());
+        assert!(intcode.pending_inputs().is_empty());
+
+        intcode.push_input(10);
+
+        assert_eq!(Ok(RunState::NeedsInput), intcode.run());
+        assert_eq!(vec![20], intcode.drain_outputs());
+        assert_eq!(Some(20), intcode.last_output());
+    }
+
+    #[test]
+    fn running_off_the_end_halts() {
+        let mut intcode = Intcode::new(&[1101, 1, 1, 5, 104, 0]);
+
+        assert_eq!(Ok(RunState::Halted), intcode.run());
+        assert!(intcode.halted());
+    }
+
+    #[test]
+    fn negative_address_should_fail() {
+        let mut intcode = Intcode::new(&[1, -1, 0, 0, 99]);
+
+        assert_eq!(
+            Err(IntcodeError::NegativeAddress { ip: 0, address: -1 }),
+            intcode.compute()
+        );
+    }
+
+    #[test]
+    fn negative_relative_address_should_fail() {
+        let mut intcode = Intcode::new(&[109, -5, 204, 1, 99]);
+
+        assert_eq!(
+            Err(IntcodeError::NegativeAddress { ip: 2, address: -4 }),
+            intcode.run()
+        );
+    }
+
+    #[test]
+    fn overflow_should_fail() {
+        let programs = [
+            (vec![1101, i64::MAX, 1, 0, 99], 0),
+            (vec![1102, i64::MAX, 2, 0, 99], 0),
+            (vec![109, i64::MAX, 109, 1, 99], 2),
+            (vec![109, i64::MAX, 204, 1, 99], 2),
+            (vec![109, i64::MIN, 21101, 1, 1, -1, 99], 2),
+        ];
+
+        for engine in Engine::ALL.iter() {
+            for (program, ip) in programs.iter() {
+                let mut intcode = Intcode::new(program);
+                intcode.set_engine(*engine);
+
+                assert_eq!(
+                    Err(IntcodeError::Overflow { ip: *ip }),
+                    intcode.run(),
+                    "{:?}",
+                    program
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_opcode_should_fail() {
+        let mut intcode = Intcode::new(&[1101, 1, 1, 5, 42, 0]);
+
+        assert_eq!(
+            Err(IntcodeError::InvalidOpcode { ip: 4, value: 42 }),
+            intcode.run()
+        );
+    }
+
+    #[test]
+    fn write_in_immediate_mode_should_fail() {
+        let mut intcode = Intcode::new(&[11101, 1, 1, 5, 99, 0]);
+
+        assert_eq!(
+            Err(IntcodeError::WriteInImmediateMode { ip: 0 }),
+            intcode.run()
+        );
+    }
+
+    #[test]
+    fn run_to_halt_without_input_should_fail() {
+        let mut intcode = Intcode::new(&[1101, 1, 1, 7, 3, 7, 99, 0]);
+
+        assert_eq!(
+            Err(IntcodeError::InputExhausted { ip: 4 }),
+            intcode.run_to_halt()
+        );
+    }
+
+    #[test]
+    fn step_limit() {
+        // Jumps to itself forever
+        let mut intcode = Intcode::new(&[1105, 1, 0]);
+        intcode.set_step_limit(Some(100));
+
+        assert_eq!(
+            Err(IntcodeError::StepLimitExceeded { limit: 100 }),
+            intcode.run()
+        );
+        assert_eq!(100, intcode.steps());
+    }
+
+    #[test]
+    fn time_limit() {
+        let mut intcode = Intcode::new(&[1105, 1, 0]);
+        intcode.set_time_limit(Some(Duration::from_millis(10)));
+
+        assert_eq!(
+            Err(IntcodeError::TimeLimitExceeded {
+                limit: Duration::from_millis(10)
+            }),
+            intcode.run()
+        );
+    }
+
+    #[test]
+    fn loop_detection() {
+        let mut intcode = Intcode::new(&[1105, 1, 0]);
+        intcode.set_loop_detection(true);
+
+        assert_eq!(Err(IntcodeError::InfiniteLoop { ip: 0 }), intcode.run());
+    }
+
+    #[test]
+    fn loop_detection_allows_progress() {
+        // Counts to 1000 in the same few instructions before halting
+        let source = "
+            loop: ADD [counter], #1 -> [counter]
+                  LT [counter], #1000 -> [more]
+                  JNZ [more], #loop
+                  OUT [counter]
+                  HALT
+            counter: DATA 0
+            more: DATA 0
+        ";
+        let mut intcode = Intcode::new(&crate::asm::assemble(source).unwrap());
+        intcode.set_loop_detection(true);
+
+        assert_eq!(Ok(RunState::Halted), intcode.run());
+        assert_eq!(Some(1000), intcode.last_output());
+    }
+
+    #[test]
+    fn loop_detection_allows_io() {
+        // Echoes forever, every pass looks the same apart from the I/O
+        let mut intcode = Intcode::new(&[3, 7, 4, 7, 1105, 1, 0, 0]);
+        intcode.set_loop_detection(true);
+
+        for i in 0..10 {
+            assert_eq!(Ok(RunState::NeedsInput), intcode.run());
+            intcode.push_input(i);
+        }
+        assert_eq!(Ok(RunState::NeedsInput), intcode.run());
+        assert_eq!((0..10).collect::<Vec<i64>>(), intcode.drain_outputs());
+    }
+
+    #[test]
+    fn run_with_vec_device() {
+        // Echoes every input doubled, forever
+        let mut intcode = Intcode::new(&[3, 9, 1002, 9, 2, 9, 4, 9, 1105, 1, 0]);
+        let mut device = VecDevice::new(vec![1, 2, 3]);
+
+        assert_eq!(Ok(RunState::NeedsInput), intcode.run_with(&mut device));
+        assert_eq!(vec![2, 4, 6], device.into_outputs());
+        assert!(intcode.outputs().is_empty());
+    }
+
+    #[test]
+    fn run_with_closure_device() {
+        let mut intcode = Intcode::new(&[3, 9, 1002, 9, 2, 9, 4, 9, 1105, 1, 0]);
+        let mut inputs = vec![5, 7].into_iter();
+        let mut sum = 0;
+
+        let mut device = ClosureDevice::new(|| inputs.next(), |v| sum += v);
+        assert_eq!(Ok(RunState::NeedsInput), intcode.run_with(&mut device));
+
+        assert_eq!(24, sum);
+    }
+
+    #[test]
+    fn run_with_channel_device() {
+        let (input_sender, input_receiver) = mpsc::channel();
+        let (output_sender, output_receiver) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            let mut intcode = Intcode::new(&[3, 9, 1002, 9, 2, 9, 4, 9, 1105, 1, 0]);
+            let mut device = ChannelDevice::new(input_receiver, output_sender);
+
+            intcode.run_with(&mut device)
+        });
+
+        input_sender.send(21).unwrap();
+        assert_eq!(Ok(42), output_receiver.recv());
+
+        drop(input_sender);
+        assert_eq!(Ok(RunState::NeedsInput), handle.join().unwrap());
+    }
+
+    #[test]
+    fn run_with_keeps_queued_inputs() {
+        let mut intcode = Intcode::new(&[3, 9, 4, 9, 99, 0, 0, 0, 0, 0]);
+        intcode.push_input(1);
+        let mut device = VecDevice::new(vec![2]);
+
+        assert_eq!(Ok(RunState::Halted), intcode.run_with(&mut device));
+        assert_eq!(&vec![2], device.outputs());
+        assert_eq!(1, intcode.pending_inputs().len());
+    }
+
+    #[test]
+    fn tracer_receives_instructions_writes_and_relative_base_changes() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut intcode = Intcode::new(&[109, 3, 21101, 2, 5, 4, 4, 7, 99]);
+        intcode.set_tracer(Arc::clone(&events));
+        intcode.run().unwrap();
+
+        let expected = vec![
+            TraceEvent::Instruction {
+                ip: 0,
+                opcode: Opcode::SetRelativeBase,
+                modes: vec![ParameterMode::Immediate],
+                operands: vec![3],
+            },
+            TraceEvent::RelativeBase { from: 0, to: 3 },
+            TraceEvent::Instruction {
+                ip: 2,
+                opcode: Opcode::Add,
+                modes: vec![
+                    ParameterMode::Immediate,
+                    ParameterMode::Immediate,
+                    ParameterMode::Relative,
+                ],
+                operands: vec![2, 5, 7],
+            },
+            TraceEvent::Write {
+                address: 7,
+                value: 7,
+            },
+            TraceEvent::Instruction {
+                ip: 6,
+                opcode: Opcode::Output,
+                modes: vec![ParameterMode::Position],
+                operands: vec![7],
+            },
+            TraceEvent::Instruction {
+                ip: 8,
+                opcode: Opcode::Halt,
+                modes: vec![],
+                operands: vec![],
+            },
+        ];
+
+        assert_eq!(expected, *events.lock().unwrap());
+    }
+
+    #[test]
+    fn blocked_input_is_traced_once() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut intcode = Intcode::new(&[3, 3, 99, 0]);
+        intcode.set_tracer(Arc::clone(&events));
+
+        assert_eq!(Ok(RunState::NeedsInput), intcode.run());
+        assert!(events.lock().unwrap().is_empty());
+
+        intcode.push_input(8);
+        assert_eq!(Ok(RunState::Halted), intcode.run());
+        assert_eq!(3, events.lock().unwrap().len());
+
+        assert!(intcode.take_tracer().is_some());
+        assert!(intcode.take_tracer().is_none());
+    }
+
+    #[test]
+    fn step_executes_one_instruction() {
+        let mut intcode = Intcode::new(&[1101, 2, 3, 9, 4, 9, 99]);
+
+        assert_eq!(Ok(None), intcode.step());
+        assert_eq!(4, intcode.position());
+        assert_eq!(5, intcode.memory(9));
+
+        assert_eq!(Ok(None), intcode.step());
+        assert_eq!(vec![5], intcode.drain_outputs());
+
+        assert_eq!(Ok(Some(RunState::Halted)), intcode.step());
+        assert_eq!(Ok(Some(RunState::Halted)), intcode.step());
+        assert_eq!(3, intcode.steps());
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        // Doubles every input forever
+        let mut intcode = Intcode::new(&[3, 11, 1002, 11, 2, 11, 4, 11, 1105, 1, 0, 0]);
+        intcode.push_inputs(vec![1, 2]);
+        intcode.run().unwrap();
+        intcode.push_input(3);
+
+        let snapshot = intcode.snapshot();
+        assert_eq!(Ok(RunState::NeedsInput), intcode.run());
+        assert_eq!(vec![2, 4, 6], intcode.drain_outputs());
+
+        intcode.restore(&snapshot).unwrap();
+        assert_eq!(2, intcode.outputs().len());
+        assert_eq!(Ok(RunState::NeedsInput), intcode.run());
+        assert_eq!(vec![2, 4, 6], intcode.drain_outputs());
+    }
+
+    #[test]
+    fn fork_runs_independently() {
+        let mut intcode = Intcode::new(&[3, 11, 1002, 11, 2, 11, 4, 11, 1105, 1, 0, 0]);
+        intcode.push_input(1);
+        intcode.run().unwrap();
+
+        let mut fork = intcode.fork();
+        fork.push_input(10);
+        intcode.push_input(20);
+        fork.run().unwrap();
+        intcode.run().unwrap();
+
+        assert_eq!(vec![2, 20], fork.drain_outputs());
+        assert_eq!(vec![2, 40], intcode.drain_outputs());
+    }
+
+    #[test]
+    fn reset() {
+        let program = vec![1101, 2, 3, 7, 4, 7, 99, 0, 0, 0];
+        let mut intcode = Intcode::new(&program);
+        intcode.run().unwrap();
+        intcode.push_input(1);
+
+        intcode.reset();
+
+        assert_eq!(&program, intcode.current_state());
+        assert!(!intcode.halted());
+        assert!(intcode.pending_inputs().is_empty());
+        assert_eq!(Ok(RunState::Halted), intcode.run());
+        assert_eq!(vec![5], intcode.drain_outputs());
+    }
+
+    #[test]
+    fn writes_to_high_addresses_are_paged() {
+        let mut intcode = Intcode::new(&[1101, 2, 3, 1_000_000_000_000, 4, 1_000_000_000_000, 99]);
+
+        assert_eq!(Ok(RunState::Halted), intcode.run());
+        assert_eq!(vec![5], intcode.drain_outputs());
+        assert!(intcode.allocated_memory() < 10_000);
+        assert_eq!(7, intcode.current_state().len());
+    }
+
+    #[test]
+    fn memory_limit() {
+        let mut intcode = Intcode::new(&[1101, 2, 3, 1_000_000_000_000, 99]);
+        intcode.set_memory_limit(Some(100));
+
+        assert_eq!(
+            Err(IntcodeError::MemoryLimitExceeded {
+                address: 1_000_000_000_000,
+                limit: 100
+            }),
+            intcode.run()
+        );
+    }
+
+    #[test]
+    fn snapshot_keeps_paged_memory() {
+        let mut intcode = Intcode::new(&[1101, 2, 3, 1_000_000_000, 99]);
+        intcode.run().unwrap();
+
+        let mut restored = Intcode::new(&[99]);
+        restored.restore(&intcode.snapshot()).unwrap();
+
+        assert_eq!(5, restored.memory(1_000_000_000));
+        assert_eq!(intcode.current_state(), restored.current_state());
+    }
+
+    #[test]
+    fn self_modifying_code_invalidates_the_decode_cache() {
+        // Runs the instruction at 4 twice, turning it from an ADD into a MUL in between
+        let source = "
+                  ADD #0, #0 -> [counter]
+            code: ADD [a], [b] -> [sum]
+                  OUT [sum]
+                  ADD #2, #0 -> [code]
+                  ADD [counter], #1 -> [counter]
+                  EQ [counter], #2 -> [done]
+                  JZ [done], #code
+                  HALT
+            a: DATA 3
+            b: DATA 4
+            sum: DATA 0
+            counter: DATA 0
+            done: DATA 0
+        ";
+        let program = crate::asm::assemble(source).unwrap();
+
+        for &cached in &[true, false] {
+            let mut intcode = Intcode::new(&program);
+            intcode.set_decode_cache(cached);
+            intcode.run().unwrap();
+
+            assert_eq!(vec![7, 12], intcode.drain_outputs());
+        }
+
+        let mut intcode = Intcode::new(&program);
+        intcode.set_engine(Engine::Compiled);
+        intcode.run().unwrap();
+
+        assert_eq!(vec![7, 12], intcode.drain_outputs());
+    }
+
+    #[test]
+    fn engines_agree() {
+        // Day 9's quine, and day 5's comparison against 8 with every input
+        let quine = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let compare = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+
+        for input in 6..=10 {
+            let run = |program: &Vec<i64>, engine: Engine| {
+                let mut intcode = Intcode::new(program);
+                intcode.set_engine(engine);
+                intcode.push_input(input);
+                intcode.run().unwrap();
+                (intcode.drain_outputs(), intcode.steps())
+            };
+
+            for program in &[&quine, &compare] {
+                assert_eq!(
+                    run(program, Engine::Interpreter),
+                    run(program, Engine::Compiled)
+                );
+            }
+        }
+
+        assert_eq!(Engine::Compiled, {
+            let mut intcode = Intcode::new(&quine);
+            intcode.set_engine(Engine::Compiled);
+            intcode.fork().engine()
+        });
+    }
+
+    #[test]
+    fn compiled_engine_reports_errors() {
+        let mut intcode = Intcode::new(&[1101, 1, 1, 5, 98, 0]);
+        intcode.set_engine(Engine::Compiled);
+
+        assert_eq!(
+            Err(IntcodeError::InvalidOpcode { ip: 4, value: 98 }),
+            intcode.run()
+        );
+    }
+
+    // Doubles each input into a cell of its own, moving the relative base along
+    const SPREADER: &str = "
+        loop: IN -> [rb+20]
+              MUL [rb+20], #2 -> [rb+20]
+              OUT [rb+20]
+              ARB #1
+              JNZ #1, #loop
+    ";
+
+    // Going back leaves memory allocated, so it's padded for snapshots to compare equal
+    fn state(intcode: &Intcode) -> Snapshot {
+        let mut snapshot = intcode.snapshot();
+        snapshot.memory.resize(64, 0);
+        snapshot
+    }
+
+    // States after every step, including the one before the first
+    fn step_through(intcode: &mut Intcode) -> Vec<Snapshot> {
+        let mut history = vec![state(intcode)];
+        while intcode.step().unwrap().is_none() {
+            history.push(state(intcode));
+        }
+
+        history
+    }
+
+    #[test]
+    fn step_back() {
+        let program = crate::asm::assemble(SPREADER).unwrap();
+        let mut intcode = Intcode::new(&program);
+        intcode.set_journal(Some(Journal::default()));
+        intcode.push_inputs(vec![1, 2, 3]);
+
+        let history = step_through(&mut intcode);
+        assert_eq!(16, history.len());
+
+        for snapshot in history[..15].iter().rev() {
+            intcode.step_back().unwrap();
+            assert_eq!(snapshot, &state(&intcode));
+        }
+
+        assert_eq!(
+            Err(IntcodeError::HistoryUnavailable { step: 0 }),
+            intcode.step_back()
+        );
+
+        intcode.run().unwrap();
+        assert_eq!(vec![2, 4, 6], intcode.drain_outputs());
+    }
+
+    #[test]
+    fn step_back_takes_back_exactly_its_output() {
+        let program = crate::asm::assemble("OUT #7\nOUT #7\nOUT #7\nHALT").unwrap();
+        let mut intcode = Intcode::new(&program);
+        intcode.set_journal(Some(Journal::default()));
+
+        intcode.step().unwrap();
+        intcode.step().unwrap();
+        let mut device = VecDevice::new(Vec::new());
+        intcode.run_with(&mut device).unwrap();
+        assert_eq!(&vec![7], device.outputs());
+
+        // The output sent to the device never was in the queue, however alike
+        intcode.goto_step(2).unwrap();
+        assert_eq!(&VecDeque::from(vec![7, 7]), intcode.outputs());
+
+        // Once the first is taken, only the second is left to come off
+        assert_eq!(Some(7), intcode.pop_output());
+        intcode.step_back().unwrap();
+        assert!(intcode.outputs().is_empty());
+        intcode.step_back().unwrap();
+        assert!(intcode.outputs().is_empty());
+        assert_eq!(0, intcode.steps());
+    }
+
+    #[test]
+    fn goto_step_replays_from_checkpoints() {
+        let program = crate::asm::assemble(SPREADER).unwrap();
+        let mut intcode = Intcode::new(&program);
+        intcode.set_journal(Some(Journal::new(3, 4, 10)));
+        intcode.push_inputs(vec![5, 6, 7, 8]);
+
+        let history = step_through(&mut intcode);
+        assert_eq!(3, intcode.journal().unwrap().len());
+
+        for &step in &[2, 9, 0, 13, 20] {
+            intcode.goto_step(step).unwrap();
+            assert_eq!(history[step as usize], state(&intcode));
+        }
+
+        let mut intcode = Intcode::new(&program);
+        intcode.set_journal(Some(Journal::new(3, 4, 1)));
+        intcode.push_inputs(vec![5, 6, 7, 8]);
+        step_through(&mut intcode);
+
+        assert_eq!(Some(17), intcode.journal().unwrap().oldest_step());
+        assert_eq!(
+            Err(IntcodeError::HistoryUnavailable { step: 16 }),
+            intcode.goto_step(16)
+        );
+    }
+
+    #[test]
+    fn rewind_to_write() {
+        let program = crate::asm::assemble(SPREADER).unwrap();
+        let mut intcode = Intcode::new(&program);
+        intcode.set_journal(Some(Journal::default()));
+        intcode.push_inputs(vec![1, 2]);
+        intcode.run().unwrap();
+
+        assert_eq!(Ok(Some(6)), intcode.rewind_to_write(21));
+        assert_eq!(2, intcode.position());
+        assert_eq!(2, intcode.memory(21));
+        assert_eq!(Ok(None), intcode.rewind_to_write(22));
+        assert_eq!(vec![2], intcode.drain_outputs());
+    }
+
+    #[test]
+    fn outside_changes_clear_the_journal() {
+        let mut intcode = Intcode::new(&[1101, 1, 1, 5, 99, 0]);
+        assert!(intcode.step_back().is_err());
+
+        intcode.set_journal(Some(Journal::default()));
+        intcode.step().unwrap();
+        intcode.set_memory(5, 7).unwrap();
+
+        assert!(intcode.step_back().is_err());
+    }
+
+    #[test]
+    fn profile() {
+        // Calls `double` twice, the way compiled Intcode does
+        let source = "
+                    ADD #first, #0 -> [rb]
+                    JNZ #1, #double
+            first:  ADD #second, #0 -> [rb]
+                    JNZ #1, #double
+            second: OUT [value]
+                    HALT
+            double: MUL [value], #2 -> [value]
+                    JZ #0, [rb]
+            value:  DATA 3
+        ";
+        let mut intcode = Intcode::new(&crate::asm::assemble(source).unwrap());
+        intcode.set_profiling(true);
+        intcode.run().unwrap();
+
+        let profile = intcode.profile().unwrap();
+        assert_eq!(10, profile.steps());
+        assert_eq!(2, profile.hits(17));
+        assert_eq!(2, profile.opcode_hits(Opcode::JumpIfTrue));
+        assert_eq!(3, profile.reads(24));
+        assert_eq!(2, profile.writes(24));
+        assert_eq!(Some(12), intcode.last_output());
+        assert_eq!("main 6\nmain;fn_0017 4", profile.folded());
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::error::IntcodeError;
+
+const PAGE_SIZE: usize = 4096;
+
+// Writes up to this far past the program image grow the dense region, anything further is paged
+const DENSE_SLACK: usize = 1 << 16;
+
+// In cells, so 128 MiB
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 24;
+
+// The program image and the addresses right after it live in a plain vector; scattered
+// high addresses get their own pages, so a write to 10^9 costs one page instead of gigabytes
+#[derive(Debug, Clone)]
+pub(crate) struct Memory {
+    dense: Vec<i64>,
+    dense_limit: usize,
+    pages: HashMap<usize, Box<[i64]>>,
+    len: usize,
+    limit: Option<usize>,
+    // Kept up to date on every write, so comparing states doesn't need a pass over memory
+    hash: u64,
+}
+
+impl Memory {
+    pub fn new(program: &[i64]) -> Self {
+        Self {
+            dense: program.to_vec(),
+            dense_limit: program.len() + DENSE_SLACK,
+            pages: HashMap::new(),
+            len: program.len(),
+            limit: Some(DEFAULT_MEMORY_LIMIT),
+            hash: hash_program(program),
+        }
+    }
+
+    pub fn get(&self, address: usize) -> i64 {
+        if address < self.dense.len() {
+            return self.dense[address];
+        }
+
+        if address < self.dense_limit {
+            return 0;
+        }
+
+        match self.pages.get(&(address / PAGE_SIZE)) {
+            Some(page) => page[address % PAGE_SIZE],
+            None => 0,
+        }
+    }
+
+    pub fn set(&mut self, address: usize, value: i64) -> Result<(), IntcodeError> {
+        let old = self.get(address);
+
+        if address < self.dense.len() {
+            self.dense[address] = value;
+        } else if address < self.dense_limit {
+            self.reserve(address + 1 - self.dense.len(), address)?;
+            self.dense.resize(address + 1, 0);
+            self.dense[address] = value;
+        } else {
+            let index = address / PAGE_SIZE;
+            if !self.pages.contains_key(&index) {
+                self.reserve(PAGE_SIZE, address)?;
+            }
+
+            let page = self
+                .pages
+                .entry(index)
+                .or_insert_with(|| vec![0; PAGE_SIZE].into_boxed_slice());
+            page[address % PAGE_SIZE] = value;
+        }
+
+        self.len = self.len.max(address + 1);
+        self.hash = self
+            .hash
+            .wrapping_sub(hash_cell(address, old))
+            .wrapping_add(hash_cell(address, value));
+
+        Ok(())
+    }
+
+    fn reserve(&self, cells: usize, address: usize) -> Result<(), IntcodeError> {
+        match self.limit {
+            Some(limit) if self.allocated() + cells > limit => {
+                Err(IntcodeError::MemoryLimitExceeded { address, limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // One past the highest address written to, or the end of the program
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    // Cells actually allocated, which is what the limit applies to
+    pub fn allocated(&self) -> usize {
+        self.dense.len() + self.pages.len() * PAGE_SIZE
+    }
+
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    // Equal for equal contents, however they are laid out
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // Whether every address holds the same value in both, which equal hashes only suggest
+    pub fn same_contents(&self, other: &Memory) -> bool {
+        let end = self.dense.len().max(other.dense.len());
+        let paged = |memory: &Memory| {
+            memory
+                .sparse()
+                .into_iter()
+                .filter(|(address, _)| *address >= end)
+                .collect::<Vec<(usize, i64)>>()
+        };
+
+        self.hash == other.hash
+            && (0..end).all(|address| self.get(address) == other.get(address))
+            && paged(self) == paged(other)
+    }
+
+    pub fn dense(&self) -> &[i64] {
+        &self.dense
+    }
+
+    // Non-zero cells outside the dense region, by address
+    pub fn sparse(&self) -> Vec<(usize, i64)> {
+        let mut cells = self
+            .pages
+            .iter()
+            .flat_map(|(index, page)| {
+                page.iter()
+                    .enumerate()
+                    .filter(|(_, value)| **value != 0)
+                    .map(move |(offset, value)| (index * PAGE_SIZE + offset, *value))
+            })
+            .collect::<Vec<(usize, i64)>>();
+
+        cells.sort_unstable();
+        cells
+    }
+
+    pub fn reset(&mut self, program: &[i64]) {
+        self.dense.clear();
+        self.dense.extend_from_slice(program);
+        self.dense_limit = program.len() + DENSE_SLACK;
+        self.pages.clear();
+        self.len = program.len();
+        self.hash = hash_program(program);
+    }
+}
+
+// A sum of per-cell hashes, where zero cells count for nothing
+fn hash_program(program: &[i64]) -> u64 {
+    program
+        .iter()
+        .enumerate()
+        .fold(0, |hash, (address, value)| {
+            hash.wrapping_add(hash_cell(address, *value))
+        })
+}
+
+fn hash_cell(address: usize, value: i64) -> u64 {
+    if value == 0 {
+        return 0;
+    }
+
+    mix(mix(address as u64) ^ value as u64)
+}
+
+// The splitmix64 finalizer
+pub(crate) fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dense_region_grows() {
+        let mut memory = Memory::new(&[1, 2, 3]);
+        memory.set(10, 4).unwrap();
+
+        assert_eq!(&[1, 2, 3, 0, 0, 0, 0, 0, 0, 0, 4], memory.dense());
+        assert_eq!(11, memory.len());
+        assert_eq!(0, memory.get(500));
+    }
+
+    #[test]
+    fn high_addresses_are_paged() {
+        let mut memory = Memory::new(&[1, 2, 3]);
+        memory.set(1_000_000_000, 7).unwrap();
+        memory.set(1_000_000_001, 8).unwrap();
+
+        assert_eq!(7, memory.get(1_000_000_000));
+        assert_eq!(0, memory.get(999_999_999));
+        assert_eq!(3 + PAGE_SIZE, memory.allocated());
+        assert_eq!(1_000_000_002, memory.len());
+        assert_eq!(
+            vec![(1_000_000_000, 7), (1_000_000_001, 8)],
+            memory.sparse()
+        );
+    }
+
+    #[test]
+    fn limit() {
+        let mut memory = Memory::new(&[1, 2, 3]);
+        memory.set_limit(Some(PAGE_SIZE + 3));
+
+        assert!(memory.set(1 << 40, 1).is_ok());
+        assert_eq!(
+            Err(IntcodeError::MemoryLimitExceeded {
+                address: 1 << 41,
+                limit: PAGE_SIZE + 3
+            }),
+            memory.set(1 << 41, 1)
+        );
+        assert!(memory.set(10, 1).is_err());
+    }
+
+    #[test]
+    fn reset() {
+        let mut memory = Memory::new(&[1, 2, 3]);
+        memory.set(1 << 30, 1).unwrap();
+        memory.set(5, 1).unwrap();
+        memory.reset(&[4]);
+
+        assert_eq!(&[4], memory.dense());
+        assert_eq!(0, memory.get(1 << 30));
+        assert_eq!(1, memory.len());
+        assert_eq!(Memory::new(&[4]).hash(), memory.hash());
+    }
+
+    #[test]
+    fn same_contents_however_laid_out() {
+        let mut memory = Memory::new(&[1, 2, 3]);
+        memory.set(1 << 30, 5).unwrap();
+        memory.set(100, 6).unwrap();
+
+        let mut other = Memory::new(&[1, 2, 3, 0]);
+        other.set(100, 6).unwrap();
+        assert!(!memory.same_contents(&other));
+
+        other.set(1 << 30, 5).unwrap();
+        assert!(memory.same_contents(&other));
+
+        memory.set(100, 0).unwrap();
+        other.set(200, 0).unwrap();
+        assert!(!memory.same_contents(&other));
+    }
+
+    #[test]
+    fn hash_follows_contents() {
+        let mut memory = Memory::new(&[1, 2, 3]);
+        memory.set(1 << 30, 5).unwrap();
+        memory.set(100, 6).unwrap();
+        assert_ne!(Memory::new(&[1, 2, 3]).hash(), memory.hash());
+
+        memory.set(1 << 30, 0).unwrap();
+        memory.set(100, 0).unwrap();
+        assert_eq!(Memory::new(&[1, 2, 3]).hash(), memory.hash());
+        assert_ne!(Memory::new(&[1, 3, 2]).hash(), memory.hash());
+    }
+}
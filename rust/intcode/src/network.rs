@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::IntcodeError;
+use crate::machine::{Intcode, RunState};
+
+// How often a machine waiting for input checks whether the whole network is stuck
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NetworkState {
+    // Every machine halted
+    Halted,
+    // The machines still running are all waiting for input nobody will send
+    Blocked,
+}
+
+#[derive(Debug)]
+struct Node {
+    name: String,
+    intcode: Intcode,
+    // Nodes that receive this node's outputs
+    links: Vec<usize>,
+    outputs: Vec<i64>,
+}
+
+// Machines wired output to input. A node sends every output to each node it's connected to,
+// so chains, rings, stars and meshes are all just different sets of links.
+#[derive(Debug, Default)]
+pub struct Network {
+    nodes: Vec<Node>,
+    names: HashMap<String, usize>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adding a name twice replaces the machine but keeps its links
+    pub fn add(&mut self, name: &str, intcode: Intcode) -> &mut Self {
+        match self.names.get(name) {
+            Some(&index) => self.nodes[index].intcode = intcode,
+            None => {
+                self.names.insert(String::from(name), self.nodes.len());
+                self.nodes.push(Node {
+                    name: String::from(name),
+                    intcode,
+                    links: Vec::new(),
+                    outputs: Vec::new(),
+                });
+            }
+        }
+
+        self
+    }
+
+    pub fn connect(&mut self, from: &str, to: &str) -> Result<&mut Self, IntcodeError> {
+        let from = self.index(from)?;
+        let to = self.index(to)?;
+
+        if !self.nodes[from].links.contains(&to) {
+            self.nodes[from].links.push(to);
+        }
+
+        Ok(self)
+    }
+
+    // Each node feeds the next one
+    pub fn chain(&mut self, names: &[&str]) -> Result<&mut Self, IntcodeError> {
+        for pair in names.windows(2) {
+            self.connect(pair[0], pair[1])?;
+        }
+
+        Ok(self)
+    }
+
+    // A chain whose last node feeds the first one
+    pub fn ring(&mut self, names: &[&str]) -> Result<&mut Self, IntcodeError> {
+        self.chain(names)?;
+
+        match (names.first(), names.last()) {
+            (Some(first), Some(last)) if names.len() > 1 => self.connect(last, first),
+            _ => Ok(self),
+        }
+    }
+
+    // The hub talks to every spoke and every spoke talks back to the hub
+    pub fn star(&mut self, hub: &str, spokes: &[&str]) -> Result<&mut Self, IntcodeError> {
+        for spoke in spokes {
+            self.connect(hub, spoke)?;
+            self.connect(spoke, hub)?;
+        }
+
+        Ok(self)
+    }
+
+    // Every node talks to every other node
+    pub fn mesh(&mut self, names: &[&str]) -> Result<&mut Self, IntcodeError> {
+        for from in names {
+            for to in names.iter().filter(|to| *to != from) {
+                self.connect(from, to)?;
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn input(&mut self, name: &str, value: i64) -> Result<&mut Self, IntcodeError> {
+        let index = self.index(name)?;
+        self.nodes[index].intcode.push_input(value);
+
+        Ok(self)
+    }
+
+    pub fn node(&self, name: &str) -> Option<&Intcode> {
+        self.names
+            .get(name)
+            .map(|&index| &self.nodes[index].intcode)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.nodes.iter().map(|node| node.name.as_str()).collect()
+    }
+
+    // Everything the node has output so far, whether or not anyone was listening
+    pub fn outputs(&self, name: &str) -> Option<&[i64]> {
+        self.names
+            .get(name)
+            .map(|&index| self.nodes[index].outputs.as_slice())
+    }
+
+    pub fn last_output(&self, name: &str) -> Option<i64> {
+        self.outputs(name)
+            .and_then(|outputs| outputs.last().copied())
+    }
+
+    // Runs the machines in turn, each until it halts or needs input, passing outputs along
+    // after every turn. Stops once a whole round goes by without any machine making progress.
+    pub fn run(&mut self) -> Result<NetworkState, IntcodeError> {
+        loop {
+            let mut progressed = false;
+
+            for index in 0..self.nodes.len() {
+                let node = &mut self.nodes[index];
+                if node.intcode.halted() {
+                    continue;
+                }
+
+                let steps = node.intcode.steps();
+                node.intcode.run()?;
+                progressed |= node.intcode.steps() != steps;
+
+                let outputs = node.intcode.drain_outputs();
+                self.deliver(index, &outputs);
+            }
+
+            if self.nodes.iter().all(|node| node.intcode.halted()) {
+                return Ok(NetworkState::Halted);
+            }
+
+            if !progressed {
+                return Ok(NetworkState::Blocked);
+            }
+        }
+    }
+
+    // Like `run`, but every machine gets its own thread and talks to the others over channels.
+    // Blocked is reported once every machine still running waits on an empty channel with no
+    // message on its way.
+    pub fn run_threaded(&mut self) -> Result<NetworkState, IntcodeError> {
+        let (senders, receivers): (Vec<Sender<i64>>, Vec<Receiver<i64>>) =
+            self.nodes.iter().map(|_| mpsc::channel()).unzip();
+        let status = Arc::new(Mutex::new(Status {
+            running: self.nodes.iter().filter(|n| !n.intcode.halted()).count(),
+            ..Status::default()
+        }));
+
+        let results = thread::scope(|scope| {
+            let handles = self
+                .nodes
+                .iter_mut()
+                .zip(receivers)
+                .map(|(node, receiver)| {
+                    let links = node
+                        .links
+                        .iter()
+                        .map(|&to| senders[to].clone())
+                        .collect::<Vec<Sender<i64>>>();
+                    let status = Arc::clone(&status);
+
+                    scope.spawn(move || run_node(node, receiver, links, &status))
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Network node panicked"))
+                .collect::<Vec<Result<(), IntcodeError>>>()
+        });
+
+        for result in results {
+            result?;
+        }
+
+        if self.nodes.iter().all(|node| node.intcode.halted()) {
+            Ok(NetworkState::Halted)
+        } else {
+            Ok(NetworkState::Blocked)
+        }
+    }
+
+    fn deliver(&mut self, from: usize, outputs: &[i64]) {
+        if outputs.is_empty() {
+            return;
+        }
+
+        for to in self.nodes[from].links.clone() {
+            self.nodes[to].intcode.push_inputs(outputs.iter().copied());
+        }
+
+        self.nodes[from].outputs.extend_from_slice(outputs);
+    }
+
+    fn index(&self, name: &str) -> Result<usize, IntcodeError> {
+        self.names
+            .get(name)
+            .copied()
+            .ok_or_else(|| IntcodeError::UnknownNode(String::from(name)))
+    }
+}
+
+// Shared by the threads of `run_threaded` to tell when nothing can happen anymore
+#[derive(Debug, Default)]
+struct Status {
+    running: usize,
+    waiting: usize,
+    in_flight: usize,
+    stopped: bool,
+}
+
+impl Status {
+    fn check(&mut self) {
+        if self.running == self.waiting && self.in_flight == 0 {
+            self.stopped = true;
+        }
+    }
+}
+
+fn lock(status: &Mutex<Status>) -> std::sync::MutexGuard<'_, Status> {
+    status.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn run_node(
+    node: &mut Node,
+    receiver: Receiver<i64>,
+    links: Vec<Sender<i64>>,
+    status: &Mutex<Status>,
+) -> Result<(), IntcodeError> {
+    if node.intcode.halted() {
+        return Ok(());
+    }
+
+    let result = run_until_stopped(node, &receiver, &links, status);
+
+    // Whatever happened, the others shouldn't wait for this machine anymore
+    let mut status = lock(status);
+    status.in_flight -= receiver.try_iter().count();
+    drop(receiver);
+    status.running -= 1;
+    if result.is_err() {
+        status.stopped = true;
+    }
+    status.check();
+
+    result
+}
+
+fn run_until_stopped(
+    node: &mut Node,
+    receiver: &Receiver<i64>,
+    links: &[Sender<i64>],
+    status: &Mutex<Status>,
+) -> Result<(), IntcodeError> {
+    loop {
+        let state = node.intcode.run()?;
+
+        for value in node.intcode.drain_outputs() {
+            node.outputs.push(value);
+
+            // Sending under the lock keeps `in_flight` exact, as machines that stop drop their
+            // receiver under it too; sends to them fail and the message is lost
+            let mut status = lock(status);
+            for link in links {
+                if link.send(value).is_ok() {
+                    status.in_flight += 1;
+                }
+            }
+        }
+
+        if state == RunState::Halted {
+            return Ok(());
+        }
+
+        {
+            let mut status = lock(status);
+            status.waiting += 1;
+            status.check();
+        }
+
+        loop {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(value) => {
+                    let mut status = lock(status);
+                    status.in_flight -= 1;
+                    status.waiting -= 1;
+                    node.intcode.push_input(value);
+                    break;
+                }
+                Err(RecvTimeoutError::Timeout) if !lock(status).stopped => continue,
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads a value, outputs it doubled and halts
+    const DOUBLE: &[i64] = &[3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+
+    // Forever reads a value and outputs it plus one, halting after passing on 10
+    const INCREMENT: &[i64] = &[
+        3, 20, 1001, 20, 1, 20, 4, 20, 1008, 20, 10, 21, 1006, 21, 0, 99, 0, 0, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn chain() {
+        let mut network = Network::new();
+        for name in &["a", "b", "c"] {
+            network.add(name, Intcode::new(DOUBLE));
+        }
+        network
+            .chain(&["a", "b", "c"])
+            .unwrap()
+            .input("a", 3)
+            .unwrap();
+
+        assert_eq!(Ok(NetworkState::Halted), network.run());
+        assert_eq!(Some(24), network.last_output("c"));
+        assert_eq!(Some(&[12][..]), network.outputs("b"));
+    }
+
+    #[test]
+    fn ring() {
+        let mut network = Network::new();
+        network
+            .add("ping", Intcode::new(INCREMENT))
+            .add("pong", Intcode::new(INCREMENT));
+        network
+            .ring(&["ping", "pong"])
+            .unwrap()
+            .input("ping", 0)
+            .unwrap();
+
+        assert_eq!(Ok(NetworkState::Blocked), network.run());
+        assert_eq!(Some(&[1, 3, 5, 7, 9, 11][..]), network.outputs("ping"));
+        assert_eq!(Some(&[2, 4, 6, 8, 10][..]), network.outputs("pong"));
+        assert!(network.node("pong").unwrap().halted());
+        assert!(!network.node("ping").unwrap().halted());
+    }
+
+    #[test]
+    fn star() {
+        let mut network = Network::new();
+        for name in &["hub", "x", "y"] {
+            network.add(name, Intcode::new(DOUBLE));
+        }
+        network
+            .star("hub", &["x", "y"])
+            .unwrap()
+            .input("hub", 1)
+            .unwrap();
+
+        assert_eq!(Ok(NetworkState::Halted), network.run());
+        assert_eq!(Some(4), network.last_output("x"));
+        assert_eq!(Some(4), network.last_output("y"));
+    }
+
+    #[test]
+    fn mesh() {
+        let mut network = Network::new();
+        for name in &["a", "b", "c"] {
+            network.add(name, Intcode::new(DOUBLE));
+        }
+        network
+            .mesh(&["a", "b", "c"])
+            .unwrap()
+            .input("a", 5)
+            .unwrap();
+
+        assert_eq!(Ok(NetworkState::Halted), network.run());
+        assert_eq!(Some(20), network.last_output("b"));
+        assert_eq!(Some(20), network.last_output("c"));
+    }
+
+    #[test]
+    fn threaded_matches_cooperative() {
+        let build = || {
+            let mut network = Network::new();
+            network
+                .add("ping", Intcode::new(INCREMENT))
+                .add("pong", Intcode::new(INCREMENT))
+                .add("echo", Intcode::new(DOUBLE));
+            network.ring(&["ping", "pong"]).unwrap();
+            network.connect("pong", "echo").unwrap();
+            network.input("ping", 0).unwrap();
+            network
+        };
+
+        let mut cooperative = build();
+        let mut threaded = build();
+
+        assert_eq!(Ok(NetworkState::Blocked), cooperative.run());
+        assert_eq!(Ok(NetworkState::Blocked), threaded.run_threaded());
+        for name in &["ping", "pong", "echo"] {
+            assert_eq!(cooperative.outputs(name), threaded.outputs(name));
+        }
+    }
+
+    #[test]
+    fn threaded_deadlock() {
+        // Both wait for the other to speak first
+        let mut network = Network::new();
+        network
+            .add("a", Intcode::new(DOUBLE))
+            .add("b", Intcode::new(DOUBLE));
+        network.ring(&["a", "b"]).unwrap();
+
+        assert_eq!(Ok(NetworkState::Blocked), network.run_threaded());
+        assert_eq!(Some(&[][..]), network.outputs("a"));
+    }
+
+    #[test]
+    fn threaded_error() {
+        let mut network = Network::new();
+        network
+            .add("a", Intcode::new(DOUBLE))
+            .add("bad", Intcode::new(&[3, 3, 98, 0]));
+        network.chain(&["a", "bad"]).unwrap().input("a", 1).unwrap();
+
+        assert_eq!(
+            Err(IntcodeError::InvalidOpcode { ip: 2, value: 98 }),
+            network.run_threaded()
+        );
+    }
+
+    #[test]
+    fn unknown_node_should_fail() {
+        let mut network = Network::new();
+        network.add("a", Intcode::new(DOUBLE));
+
+        assert_eq!(
+            Some(IntcodeError::UnknownNode(String::from("b"))),
+            network.connect("a", "b").err()
+        );
+    }
+}
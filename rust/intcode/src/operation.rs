@@ -0,0 +1,207 @@
+use crate::error::IntcodeError;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ParameterMode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Set,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    SetRelativeBase,
+    Halt,
+}
+
+impl Opcode {
+    pub fn code(self) -> i64 {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Multiply => 2,
+            Opcode::Set => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::Equals => 8,
+            Opcode::SetRelativeBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        match mnemonic.to_uppercase().as_str() {
+            "ADD" => Some(Opcode::Add),
+            "MUL" => Some(Opcode::Multiply),
+            "IN" => Some(Opcode::Set),
+            "OUT" => Some(Opcode::Output),
+            "JNZ" => Some(Opcode::JumpIfTrue),
+            "JZ" => Some(Opcode::JumpIfFalse),
+            "LT" => Some(Opcode::LessThan),
+            "EQ" => Some(Opcode::Equals),
+            "ARB" => Some(Opcode::SetRelativeBase),
+            "HALT" => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "ADD",
+            Opcode::Multiply => "MUL",
+            Opcode::Set => "IN",
+            Opcode::Output => "OUT",
+            Opcode::JumpIfTrue => "JNZ",
+            Opcode::JumpIfFalse => "JZ",
+            Opcode::LessThan => "LT",
+            Opcode::Equals => "EQ",
+            Opcode::SetRelativeBase => "ARB",
+            Opcode::Halt => "HALT",
+        }
+    }
+
+    pub fn parameter_count(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Set | Opcode::Output | Opcode::SetRelativeBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    // Index of the parameter the instruction stores its result in, if any
+    pub fn result_parameter(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => Some(2),
+            Opcode::Set => Some(0),
+            _ => None,
+        }
+    }
+}
+
+impl ParameterMode {
+    pub fn code(self) -> i64 {
+        match self {
+            ParameterMode::Position => 0,
+            ParameterMode::Immediate => 1,
+            ParameterMode::Relative => 2,
+        }
+    }
+}
+
+pub fn parse_operation(
+    ip: usize,
+    operation: i64,
+) -> Result<(Opcode, ParameterMode, ParameterMode, ParameterMode), IntcodeError> {
+    let invalid_mode = || IntcodeError::InvalidMode {
+        ip,
+        value: operation,
+    };
+
+    let opcode = parse_opcode(operation % 100).ok_or(IntcodeError::InvalidOpcode {
+        ip,
+        value: operation,
+    })?;
+    let first_parameter_mode = parse_mode(operation / 100 % 10).ok_or_else(invalid_mode)?;
+    let second_parameter_mode = parse_mode(operation / 1000 % 10).ok_or_else(invalid_mode)?;
+    let third_parameter_mode = parse_mode(operation / 10000 % 10).ok_or_else(invalid_mode)?;
+
+    Ok((
+        opcode,
+        first_parameter_mode,
+        second_parameter_mode,
+        third_parameter_mode,
+    ))
+}
+
+pub fn parse_opcode(opcode: i64) -> Option<Opcode> {
+    match opcode % 100 {
+        1 => Some(Opcode::Add),
+        2 => Some(Opcode::Multiply),
+        3 => Some(Opcode::Set),
+        4 => Some(Opcode::Output),
+        5 => Some(Opcode::JumpIfTrue),
+        6 => Some(Opcode::JumpIfFalse),
+        7 => Some(Opcode::LessThan),
+        8 => Some(Opcode::Equals),
+        9 => Some(Opcode::SetRelativeBase),
+        99 => Some(Opcode::Halt),
+        _ => None,
+    }
+}
+
+pub fn parse_mode(mode: i64) -> Option<ParameterMode> {
+    match mode {
+        0 => Some(ParameterMode::Position),
+        1 => Some(ParameterMode::Immediate),
+        2 => Some(ParameterMode::Relative),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_operation_test() {
+        let operation = 1002;
+        assert_eq!(
+            Ok((
+                Opcode::Multiply,
+                ParameterMode::Position,
+                ParameterMode::Immediate,
+                ParameterMode::Position
+            )),
+            parse_operation(0, operation)
+        );
+    }
+
+    #[test]
+    fn parse_operation_relative_test() {
+        let operation = 21201;
+        assert_eq!(
+            Ok((
+                Opcode::Add,
+                ParameterMode::Relative,
+                ParameterMode::Immediate,
+                ParameterMode::Relative
+            )),
+            parse_operation(0, operation)
+        );
+    }
+
+    #[test]
+    fn parse_operation_invalid_opcode_should_fail() {
+        assert_eq!(
+            Err(IntcodeError::InvalidOpcode { ip: 3, value: 42 }),
+            parse_operation(3, 42)
+        );
+    }
+
+    #[test]
+    fn parse_operation_invalid_mode_should_fail() {
+        assert_eq!(
+            Err(IntcodeError::InvalidMode { ip: 3, value: 301 }),
+            parse_operation(3, 301)
+        );
+    }
+
+    #[test]
+    fn opcode_codes_and_mnemonics_round_trip() {
+        for code in (1..=9).chain(vec![99]) {
+            let opcode = parse_opcode(code).unwrap();
+
+            assert_eq!(code, opcode.code());
+            assert_eq!(Some(opcode), Opcode::from_mnemonic(opcode.mnemonic()));
+        }
+    }
+}
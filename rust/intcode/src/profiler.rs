@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::operation::{Opcode, ParameterMode};
+
+// Rows shown in each table of the summary
+const TOP: usize = 10;
+
+// Cells per row of the memory heatmap
+const REGION_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    entry: usize,
+    return_to: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Previous {
+    ip: usize,
+    opcode: Opcode,
+    target_mode: ParameterMode,
+}
+
+// Where a program spends its time, recorded while the machine runs with profiling on.
+//
+// Intcode has no call instruction, so calls are recognised by the pattern compilers emit: an
+// instruction writes the address right after the following jump, then the jump is taken to an
+// immediate target. A taken jump to an address read from memory that matches one of those return
+// addresses is a return.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    steps: u64,
+    instructions: HashMap<usize, (Opcode, u64)>,
+    opcodes: HashMap<Opcode, u64>,
+    reads: HashMap<usize, u64>,
+    writes: HashMap<usize, u64>,
+    max_relative_base: i64,
+    stack: Vec<Frame>,
+    // Call stacks by function entry points, and the instructions executed in each
+    stacks: HashMap<Vec<usize>, usize>,
+    stack_steps: Vec<u64>,
+    current_stack: Option<usize>,
+    previous: Option<Previous>,
+    previous_write: Option<i64>,
+    last_write: Option<i64>,
+}
+
+impl Profile {
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn hits(&self, address: usize) -> u64 {
+        self.instructions.get(&address).map_or(0, |(_, hits)| *hits)
+    }
+
+    pub fn opcode_hits(&self, opcode: Opcode) -> u64 {
+        self.opcodes.get(&opcode).copied().unwrap_or(0)
+    }
+
+    pub fn reads(&self, address: usize) -> u64 {
+        self.reads.get(&address).copied().unwrap_or(0)
+    }
+
+    pub fn writes(&self, address: usize) -> u64 {
+        self.writes.get(&address).copied().unwrap_or(0)
+    }
+
+    pub fn max_relative_base(&self) -> i64 {
+        self.max_relative_base
+    }
+
+    pub(crate) fn instruction(&mut self, ip: usize, opcode: Opcode, modes: [ParameterMode; 3]) {
+        if let Some(previous) = self.previous {
+            self.follow_jump(previous, ip);
+        }
+
+        self.previous = Some(Previous {
+            ip,
+            opcode,
+            target_mode: modes[1],
+        });
+        self.previous_write = self.last_write.take();
+
+        self.steps += 1;
+        self.instructions.entry(ip).or_insert((opcode, 0)).1 += 1;
+        *self.opcodes.entry(opcode).or_insert(0) += 1;
+
+        let stack = match self.current_stack {
+            Some(stack) => stack,
+            None => self.intern_stack(),
+        };
+        self.stack_steps[stack] += 1;
+    }
+
+    pub(crate) fn read(&mut self, address: usize) {
+        *self.reads.entry(address).or_insert(0) += 1;
+    }
+
+    pub(crate) fn write(&mut self, address: usize, value: i64) {
+        *self.writes.entry(address).or_insert(0) += 1;
+        self.last_write = Some(value);
+    }
+
+    pub(crate) fn relative_base(&mut self, relative_base: i64) {
+        self.max_relative_base = self.max_relative_base.max(relative_base);
+    }
+
+    fn follow_jump(&mut self, previous: Previous, ip: usize) {
+        let is_jump =
+            previous.opcode == Opcode::JumpIfTrue || previous.opcode == Opcode::JumpIfFalse;
+        let return_to = previous.ip + 3;
+
+        if !is_jump || ip == return_to {
+            return;
+        }
+
+        if previous.target_mode == ParameterMode::Immediate {
+            if self.previous_write == Some(return_to as i64) {
+                self.stack.push(Frame {
+                    entry: ip,
+                    return_to,
+                });
+                self.current_stack = None;
+            }
+        } else if let Some(i) = self.stack.iter().rposition(|f| f.return_to == ip) {
+            self.stack.truncate(i);
+            self.current_stack = None;
+        }
+    }
+
+    fn intern_stack(&mut self) -> usize {
+        let key = self.stack.iter().map(|f| f.entry).collect::<Vec<usize>>();
+        let next = self.stack_steps.len();
+        let id = *self.stacks.entry(key).or_insert(next);
+
+        if id == next {
+            self.stack_steps.push(0);
+        }
+
+        self.current_stack = Some(id);
+        id
+    }
+
+    // Busiest instructions and opcodes, the hottest memory regions and the stack high-water mark
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+
+        writeln!(summary, "Instructions executed: {}", self.steps).unwrap();
+        writeln!(summary, "Max relative base: {}", self.max_relative_base).unwrap();
+
+        writeln!(summary, "\nHottest instructions:").unwrap();
+        writeln!(
+            summary,
+            "{:>12} {:>6}  {:<4}  {:>6}",
+            "hits", "%", "ip", "opcode"
+        )
+        .unwrap();
+        let mut instructions = self.instructions.iter().collect::<Vec<_>>();
+        instructions.sort_by_key(|(address, (_, hits))| (std::cmp::Reverse(*hits), **address));
+        for (address, (opcode, hits)) in instructions.into_iter().take(TOP) {
+            writeln!(
+                summary,
+                "{:>12} {:>6.2}  {:04}  {:>6}",
+                hits,
+                self.percent(*hits),
+                address,
+                opcode.mnemonic()
+            )
+            .unwrap();
+        }
+
+        writeln!(summary, "\nOpcodes:").unwrap();
+        writeln!(summary, "{:>12} {:>6}  {:>6}", "hits", "%", "opcode").unwrap();
+        let mut opcodes = self.opcodes.iter().collect::<Vec<_>>();
+        opcodes.sort_by_key(|(opcode, hits)| (std::cmp::Reverse(**hits), opcode.code()));
+        for (opcode, hits) in opcodes {
+            writeln!(
+                summary,
+                "{:>12} {:>6.2}  {:>6}",
+                hits,
+                self.percent(*hits),
+                opcode.mnemonic()
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            summary,
+            "\nMemory heatmap ({} cells per region):",
+            REGION_SIZE
+        )
+        .unwrap();
+        writeln!(summary, "{:>12} {:>12}  region", "reads", "writes").unwrap();
+        let mut regions = HashMap::new();
+        for (address, reads) in &self.reads {
+            regions.entry(address / REGION_SIZE).or_insert((0, 0)).0 += reads;
+        }
+        for (address, writes) in &self.writes {
+            regions.entry(address / REGION_SIZE).or_insert((0, 0)).1 += writes;
+        }
+        let mut regions = regions.into_iter().collect::<Vec<_>>();
+        regions
+            .sort_by_key(|(region, (reads, writes))| (std::cmp::Reverse(reads + writes), *region));
+        for (region, (reads, writes)) in regions.into_iter().take(TOP) {
+            let start = region * REGION_SIZE;
+            writeln!(
+                summary,
+                "{:>12} {:>12}  {:04}..{:04}",
+                reads,
+                writes,
+                start,
+                start + REGION_SIZE
+            )
+            .unwrap();
+        }
+
+        summary
+    }
+
+    // One line per call stack in the format flamegraph.pl and inferno read, functions being
+    // named after their entry point
+    pub fn folded(&self) -> String {
+        let mut lines = self
+            .stacks
+            .iter()
+            .filter(|(_, id)| self.stack_steps[**id] > 0)
+            .map(|(stack, id)| {
+                let frames = std::iter::once(String::from("main"))
+                    .chain(stack.iter().map(|entry| format!("fn_{:04}", entry)))
+                    .collect::<Vec<String>>()
+                    .join(";");
+
+                format!("{} {}", frames, self.stack_steps[*id])
+            })
+            .collect::<Vec<String>>();
+
+        lines.sort();
+        lines.join("\n")
+    }
+
+    fn percent(&self, hits: u64) -> f64 {
+        100.0 * hits as f64 / self.steps.max(1) as f64
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use crate::compiler::Engine;
+use crate::limits::Limits;
+use crate::machine::Intcode;
+
+// A parsed program and how every machine made from it runs, so whoever runs a solution decides
+// how far its machines may go and which engine they use
+#[derive(Debug, PartialEq, Clone)]
+pub struct Program {
+    code: Vec<i64>,
+    limits: Limits,
+    engine: Engine,
+}
+
+impl Program {
+    pub fn new(code: Vec<i64>) -> Self {
+        Self {
+            code,
+            limits: Limits::default(),
+            engine: Engine::default(),
+        }
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    // Applies what a runner sets by name, keeping anything it leaves out: the `engine`, and the
+    // limits `steps`, `time` in seconds and `detect-loops`
+    pub fn settings<'a, F>(mut self, setting: F) -> Result<Self, String>
+    where
+        F: Fn(&str) -> Option<&'a str>,
+    {
+        let invalid = |name: &str, value: &str| format!("Invalid value for {}: {}", name, value);
+
+        if let Some(value) = setting("engine") {
+            self.engine = Engine::new(value)?;
+        }
+
+        if let Some(value) = setting("steps") {
+            let steps = value.parse().map_err(|_| invalid("steps", value))?;
+            self.limits.steps = Some(steps);
+        }
+
+        if let Some(value) = setting("time") {
+            let seconds = value
+                .parse::<f64>()
+                .ok()
+                .filter(|s| s.is_finite() && *s >= 0.0)
+                .ok_or_else(|| invalid("time", value))?;
+            self.limits.time = Some(Duration::from_secs_f64(seconds));
+        }
+
+        if let Some(value) = setting("detect-loops") {
+            self.limits.detect_loops = value.parse().map_err(|_| invalid("detect-loops", value))?;
+        }
+
+        Ok(self)
+    }
+
+    pub fn code(&self) -> &[i64] {
+        &self.code
+    }
+
+    // Different code run the same way, such as this program with a few cells patched
+    pub fn with_code(&self, code: Vec<i64>) -> Self {
+        Self {
+            code,
+            ..self.clone()
+        }
+    }
+
+    pub fn machine(&self) -> Intcode {
+        let mut intcode = Intcode::new(&self.code);
+        intcode.set_limits(self.limits);
+        intcode.set_engine(self.engine);
+        intcode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::IntcodeError;
+
+    #[test]
+    fn machines_start_with_the_settings() {
+        let limits = Limits {
+            steps: Some(1),
+            time: None,
+            detect_loops: false,
+        };
+        let program = Program::new(vec![1101, 1, 1, 0, 99])
+            .limits(limits)
+            .engine(Engine::Compiled);
+
+        assert_eq!(limits, program.machine().limits());
+        assert_eq!(Engine::Compiled, program.machine().engine());
+        assert_eq!(
+            Err(IntcodeError::StepLimitExceeded { limit: 1 }),
+            program.machine().run()
+        );
+        assert_eq!(limits, program.with_code(vec![99]).machine().limits());
+        assert_eq!(Limits::default(), Intcode::new(program.code()).limits());
+        assert_eq!(Engine::Interpreter, Intcode::new(program.code()).engine());
+    }
+
+    #[test]
+    fn settings_by_name() {
+        let settings = |name: &str| match name {
+            "steps" => Some("10"),
+            "time" => Some("1.5"),
+            "engine" => Some("compiled"),
+            _ => None,
+        };
+        let program = Program::new(vec![99]).settings(settings).unwrap();
+
+        assert_eq!(
+            Limits {
+                steps: Some(10),
+                time: Some(Duration::from_millis(1500)),
+                detect_loops: false,
+            },
+            program.machine().limits()
+        );
+        assert_eq!(Engine::Compiled, program.machine().engine());
+        let detect_loops = |name: &str| Some("yes").filter(|_| name == "detect-loops");
+        assert_eq!(
+            Err(String::from("Invalid value for detect-loops: yes")),
+            Program::new(vec![99]).settings(detect_loops).map(|_| ())
+        );
+    }
+}
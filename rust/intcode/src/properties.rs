@@ -0,0 +1,344 @@
+// Properties of Intcode semantics, checked on random cases against the shared machine. Every
+// test uses a fixed seed, so a failure can be repeated.
+
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::compiler::Engine;
+use crate::error::IntcodeError;
+use crate::fuzz::{self, Rng};
+use crate::limits::Limits;
+use crate::machine::{Intcode, RunState};
+use crate::operation::{Opcode, ParameterMode};
+use crate::snapshot::Snapshot;
+
+const CASES: usize = 20;
+
+// Single instructions run at CODE, after an ARB that sets the relative base, and read their
+// parameters from the cells starting at DATA
+const CODE: usize = 2;
+const DATA: usize = 16;
+const SIZE: usize = 64;
+
+const OPCODES: [Opcode; 9] = [
+    Opcode::Add,
+    Opcode::Multiply,
+    Opcode::Set,
+    Opcode::Output,
+    Opcode::JumpIfTrue,
+    Opcode::JumpIfFalse,
+    Opcode::LessThan,
+    Opcode::Equals,
+    Opcode::SetRelativeBase,
+];
+
+const MODES: [ParameterMode; 3] = [
+    ParameterMode::Position,
+    ParameterMode::Immediate,
+    ParameterMode::Relative,
+];
+
+// What a single instruction should leave behind
+#[derive(Debug, PartialEq)]
+struct Expected {
+    memory: Vec<i64>,
+    position: usize,
+    relative_base: i64,
+    outputs: Vec<i64>,
+}
+
+// Every combination of modes for the given number of parameters
+fn modes(count: usize) -> Vec<Vec<ParameterMode>> {
+    (0..count).fold(vec![Vec::new()], |combinations, _| {
+        combinations
+            .iter()
+            .flat_map(|modes| {
+                MODES.iter().map(move |&mode| {
+                    let mut modes = modes.clone();
+                    modes.push(mode);
+                    modes
+                })
+            })
+            .collect()
+    })
+}
+
+// A program running `opcode` with `modes` on random data, and the input it reads, if any
+fn instruction(rng: &mut Rng, opcode: Opcode, modes: &[ParameterMode]) -> (Vec<i64>, i64) {
+    let relative_base = rng.below(SIZE - DATA) as i64;
+    let mut memory = vec![0; SIZE];
+    memory[0] = 109;
+    memory[1] = relative_base;
+
+    // Small values, so comparisons are often equal and conditions often zero
+    for cell in memory.iter_mut().skip(DATA) {
+        *cell = rng.between(-3, 3);
+    }
+
+    let mut code = opcode.code();
+    for (i, &mode) in modes.iter().enumerate() {
+        let is_target = (opcode == Opcode::JumpIfTrue || opcode == Opcode::JumpIfFalse) && i == 1;
+        let address = DATA + rng.below(SIZE - DATA);
+
+        // Jumps land somewhere in memory
+        let parameter = match mode {
+            ParameterMode::Immediate if is_target => rng.below(SIZE) as i64,
+            ParameterMode::Immediate => rng.between(-3, 3),
+            ParameterMode::Position => address as i64,
+            ParameterMode::Relative => address as i64 - relative_base,
+        };
+        if is_target && mode != ParameterMode::Immediate {
+            memory[address] = rng.below(SIZE) as i64;
+        }
+
+        code += mode.code() * 10i64.pow(i as u32 + 2);
+        memory[CODE + i + 1] = parameter;
+    }
+
+    memory[CODE] = code;
+    memory[CODE + modes.len() + 1] = 99;
+
+    (memory, rng.between(-3, 3))
+}
+
+// Works out by hand what the instruction at CODE does
+fn evaluate(memory: &[i64], input: i64) -> Expected {
+    let mut memory = memory.to_vec();
+    let mut relative_base = memory[1];
+    let base = relative_base;
+    let code = memory[CODE];
+    let parameter = |i: usize| memory[CODE + i + 1];
+    let address = |i: usize| match code / 10i64.pow(i as u32 + 2) % 10 {
+        0 => parameter(i) as usize,
+        _ => (base + parameter(i)) as usize,
+    };
+    let value = |i: usize| match code / 10i64.pow(i as u32 + 2) % 10 {
+        1 => parameter(i),
+        _ => memory[address(i)],
+    };
+
+    let mut jump = None;
+    let mut outputs = Vec::new();
+    let mut write = None;
+
+    match code % 100 {
+        1 => write = Some((address(2), value(0) + value(1))),
+        2 => write = Some((address(2), value(0) * value(1))),
+        3 => write = Some((address(0), input)),
+        4 => outputs.push(value(0)),
+        5 if value(0) != 0 => jump = Some(value(1) as usize),
+        6 if value(0) == 0 => jump = Some(value(1) as usize),
+        7 => write = Some((address(2), (value(0) < value(1)) as i64)),
+        8 => write = Some((address(2), (value(0) == value(1)) as i64)),
+        9 => relative_base += value(0),
+        _ => {}
+    }
+
+    let count = match code % 100 {
+        1 | 2 | 7 | 8 => 3,
+        5 | 6 => 2,
+        _ => 1,
+    };
+    let position = jump.unwrap_or(CODE + count + 1);
+
+    if let Some((address, value)) = write {
+        memory[address] = value;
+    }
+
+    Expected {
+        memory,
+        position,
+        relative_base,
+        outputs,
+    }
+}
+
+#[test]
+fn every_opcode_and_mode() {
+    let mut rng = Rng::new(25);
+
+    for &opcode in OPCODES.iter() {
+        for modes in modes(opcode.parameter_count()) {
+            let writes_immediate = opcode
+                .result_parameter()
+                .is_some_and(|i| modes[i] == ParameterMode::Immediate);
+
+            for _ in 0..CASES {
+                let (program, input) = instruction(&mut rng, opcode, &modes);
+                let mut intcode = Intcode::new(&program);
+                intcode.push_input(input);
+
+                assert_eq!(Ok(None), intcode.step());
+
+                if writes_immediate {
+                    assert_eq!(
+                        Err(IntcodeError::WriteInImmediateMode { ip: CODE }),
+                        intcode.step(),
+                        "{:?}",
+                        program
+                    );
+                    continue;
+                }
+
+                assert!(intcode.step().is_ok(), "{:?}", program);
+
+                let actual = Expected {
+                    memory: (0..SIZE).map(|a| intcode.memory(a)).collect(),
+                    position: intcode.position(),
+                    relative_base: intcode.relative_base(),
+                    outputs: intcode.drain_outputs(),
+                };
+
+                assert_eq!(evaluate(&program, input), actual, "{:?}", program);
+            }
+        }
+    }
+}
+
+#[test]
+fn relative_base_moves_both_ways() {
+    let mut rng = Rng::new(9);
+
+    for _ in 0..CASES * 10 {
+        let mut program = Vec::new();
+        let mut relative_base = 0i64;
+        let mut written = BTreeMap::new();
+        let mut outputs = Vec::new();
+
+        for _ in 0..rng.below(10) + 1 {
+            // The base mostly climbs past the cells written, so their offsets are negative
+            let adjustment = rng.between(-20, 60).max(-relative_base);
+            relative_base += adjustment;
+
+            // Every cell written is past the end of the program
+            let address = 100 + rng.below(200) as i64;
+            let offset = address - relative_base;
+            let (a, b) = (rng.between(-50, 50), rng.between(-50, 50));
+
+            program.extend(vec![109, adjustment, 21101, a, b, offset, 204, offset]);
+            written.insert(address as usize, a + b);
+            outputs.push(a + b);
+        }
+        program.push(99);
+
+        let mut intcode = Intcode::new(&program);
+
+        assert_eq!(Ok(RunState::Halted), intcode.run(), "{:?}", program);
+        assert_eq!(relative_base, intcode.relative_base());
+        assert_eq!(outputs, intcode.drain_outputs());
+        for (&address, &value) in written.iter() {
+            assert_eq!(value, intcode.memory(address), "{:?}", program);
+        }
+    }
+}
+
+#[test]
+fn relative_addresses_below_zero_fail() {
+    let mut rng = Rng::new(10);
+
+    for _ in 0..CASES * 10 {
+        let relative_base = rng.between(-50, 50);
+        let offset = -relative_base - 1 - rng.between(0, 50);
+        let program = vec![109, relative_base, 21101, 1, 1, offset, 99];
+        let mut intcode = Intcode::new(&program);
+
+        assert_eq!(
+            Err(IntcodeError::NegativeAddress {
+                ip: 2,
+                address: relative_base + offset
+            }),
+            intcode.run()
+        );
+    }
+}
+
+#[test]
+fn memory_grows_beyond_the_program() {
+    let mut rng = Rng::new(11);
+
+    for _ in 0..CASES * 10 {
+        // Sometimes just past the end, sometimes far enough to be paged
+        let address = match rng.below(2) {
+            0 => 9 + rng.below(64),
+            _ => 9 + rng.below(1 << 20),
+        } as i64;
+        let value = rng.between(-1_000_000, 1_000_000);
+        let unwritten = address + 1 + rng.below(1000) as i64;
+        let program = vec![1101, value, 0, address, 4, address, 4, unwritten, 99];
+        let mut intcode = Intcode::new(&program);
+
+        assert_eq!(Ok(RunState::Halted), intcode.run());
+        assert_eq!(vec![value, 0], intcode.drain_outputs());
+        assert_eq!(value, intcode.memory(address as usize));
+        assert_eq!(0, intcode.memory(unwritten as usize));
+        assert_eq!(&program[..], &intcode.current_state()[..program.len()]);
+    }
+}
+
+// How a run ended, its outputs and the state it was left in, or nothing if it panicked
+type Run = Option<(Result<RunState, IntcodeError>, Vec<i64>, Snapshot)>;
+
+// Runs with every input given up front, or with each given only once the machine asks for it.
+// Outputs are drained at random while paused.
+fn run(program: &[i64], inputs: &[i64], engine: Engine, paused: Option<&mut Rng>) -> Run {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut intcode = Intcode::new(program);
+        intcode.set_limits(Limits {
+            steps: Some(10_000),
+            time: None,
+            detect_loops: false,
+        });
+        intcode.set_engine(engine);
+
+        let mut outputs = Vec::new();
+        let result = match paused {
+            None => {
+                intcode.push_inputs(inputs.iter().copied());
+                intcode.run()
+            }
+            Some(rng) => {
+                let mut inputs = inputs.iter();
+                loop {
+                    let result = intcode.run();
+                    if rng.below(2) == 0 {
+                        outputs.extend(intcode.drain_outputs());
+                    }
+
+                    match (&result, inputs.next()) {
+                        (Ok(RunState::NeedsInput), Some(&input)) => intcode.push_input(input),
+                        _ => break result,
+                    }
+                }
+            }
+        };
+
+        outputs.extend(intcode.drain_outputs());
+        let mut state = intcode.snapshot();
+        // Inputs left over when the program finished early were never given to the paused one
+        state.inputs.clear();
+
+        (result, outputs, state)
+    }))
+    .ok()
+}
+
+#[test]
+fn outputs_are_never_lost_when_input_is_paused() {
+    let mut rng = Rng::new(12);
+    let opcodes = fuzz::day_opcodes(9).unwrap();
+
+    for _ in 0..CASES * 10 {
+        let length = rng.below(24) + 1;
+        let program = fuzz::generate(&mut rng, &opcodes, length);
+        let inputs = (0..rng.below(5))
+            .map(|_| rng.between(-10, 10))
+            .collect::<Vec<i64>>();
+
+        for &engine in Engine::ALL.iter() {
+            let all_at_once = run(&program, &inputs, engine, None);
+            let paused = run(&program, &inputs, engine, Some(&mut rng));
+
+            assert_eq!(all_at_once, paused, "{:?} with {:?}", program, inputs);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+const HEADER: &str = "intcode-snapshot 1";
+
+// Everything needed to resume a machine from where it was paused
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snapshot {
+    pub memory: Vec<i64>,
+    // Non-zero cells beyond `memory`, by address
+    pub sparse: Vec<(usize, i64)>,
+    pub position: usize,
+    pub relative_base: i64,
+    pub inputs: Vec<i64>,
+    pub outputs: Vec<i64>,
+    pub last_output: Option<i64>,
+    pub halted: bool,
+    pub steps: u64,
+}
+
+impl Snapshot {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        fs::write(path, self.to_string()).map_err(|e| format!("Failed to save snapshot: {}", e))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Failed to load snapshot: {}", e))?;
+
+        contents.parse()
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let last_output = match self.last_output {
+            Some(value) => value.to_string(),
+            None => String::from("none"),
+        };
+
+        let fields = vec![
+            ("halted", self.halted.to_string()),
+            ("position", self.position.to_string()),
+            ("relative_base", self.relative_base.to_string()),
+            ("last_output", last_output),
+            ("steps", self.steps.to_string()),
+            ("inputs", join(&self.inputs)),
+            ("outputs", join(&self.outputs)),
+            ("memory", join(&self.memory)),
+            (
+                "sparse",
+                self.sparse
+                    .iter()
+                    .map(|(address, value)| format!("{}={}", address, value))
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+        ];
+
+        writeln!(f, "{}", HEADER)?;
+        for (name, value) in fields {
+            match value.as_str() {
+                "" => writeln!(f, "{}", name)?,
+                value => writeln!(f, "{} {}", name, value)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+
+        if lines.next() != Some(HEADER) {
+            return Err(String::from("Not an Intcode snapshot"));
+        }
+
+        let mut field = |name: &str| -> Result<String, String> {
+            let line = lines.next().unwrap_or("");
+            match line.strip_prefix(name) {
+                Some(value) if value.is_empty() || value.starts_with(' ') => {
+                    Ok(String::from(value.trim()))
+                }
+                _ => Err(format!("Expected {} in snapshot, got: {}", name, line)),
+            }
+        };
+
+        let halted = parse(&field("halted")?)?;
+        let position = parse(&field("position")?)?;
+        let relative_base = parse(&field("relative_base")?)?;
+        let last_output = match field("last_output")?.as_str() {
+            "none" => None,
+            value => Some(parse(value)?),
+        };
+        let steps = parse(&field("steps")?)?;
+        let inputs = split(&field("inputs")?)?;
+        let outputs = split(&field("outputs")?)?;
+        let memory = split(&field("memory")?)?;
+        let sparse = field("sparse")?
+            .split(',')
+            .filter(|cell| !cell.is_empty())
+            .map(|cell| match cell.find('=') {
+                Some(i) => Ok((parse(&cell[..i])?, parse(&cell[i + 1..])?)),
+                None => Err(format!("Invalid value in snapshot: {}", cell)),
+            })
+            .collect::<Result<Vec<(usize, i64)>, String>>()?;
+
+        Ok(Snapshot {
+            memory,
+            sparse,
+            position,
+            relative_base,
+            inputs,
+            outputs,
+            last_output,
+            halted,
+            steps,
+        })
+    }
+}
+
+fn join(values: &[i64]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn split(values: &str) -> Result<Vec<i64>, String> {
+    if values.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    values.split(',').map(parse).collect()
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid value in snapshot: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            memory: vec![3, 5, 99, 0, -7],
+            sparse: vec![(100000, 4), (100001, -1)],
+            position: 2,
+            relative_base: -4,
+            inputs: vec![1, 2],
+            outputs: vec![],
+            last_output: Some(12),
+            halted: false,
+            steps: 31,
+        }
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let text = snapshot().to_string();
+
+        assert_eq!(
+            "\
+intcode-snapshot 1
+halted false
+position 2
+relative_base -4
+last_output 12
+steps 31
+inputs 1,2
+outputs
+memory 3,5,99,0,-7
+sparse 100000=4,100001=-1
+",
+            text
+        );
+        assert_eq!(Ok(snapshot()), text.parse());
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let path = std::env::temp_dir().join(format!("intcode-snapshot-{}", std::process::id()));
+
+        snapshot().save(&path).unwrap();
+        let loaded = Snapshot::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(Ok(snapshot()), loaded);
+    }
+
+    #[test]
+    fn invalid_snapshot_should_fail() {
+        assert!("1,2,3".parse::<Snapshot>().is_err());
+        assert!(snapshot()
+            .to_string()
+            .replace("position 2", "position -2")
+            .parse::<Snapshot>()
+            .is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use std::io::{self, Stderr, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::operation::{Opcode, ParameterMode};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TraceEvent {
+    // Operands are resolved: values for inputs, addresses for results and jump targets
+    Instruction {
+        ip: usize,
+        opcode: Opcode,
+        modes: Vec<ParameterMode>,
+        operands: Vec<i64>,
+    },
+    Write {
+        address: usize,
+        value: i64,
+    },
+    RelativeBase {
+        from: i64,
+        to: i64,
+    },
+}
+
+pub trait Tracer {
+    fn trace(&mut self, event: &TraceEvent) -> Result<(), String>;
+}
+
+// Lets the caller keep a handle on the tracer while the machine owns a clone
+impl<T: Tracer> Tracer for Arc<Mutex<T>> {
+    fn trace(&mut self, event: &TraceEvent) -> Result<(), String> {
+        match self.lock() {
+            Ok(mut tracer) => tracer.trace(event),
+            Err(_) => Err(String::from("Tracer lock is poisoned")),
+        }
+    }
+}
+
+impl Tracer for Vec<TraceEvent> {
+    fn trace(&mut self, event: &TraceEvent) -> Result<(), String> {
+        self.push(event.clone());
+        Ok(())
+    }
+}
+
+// Human readable listing, one line per event
+pub struct PrettyTracer<W: Write> {
+    writer: W,
+}
+
+impl PrettyTracer<Stderr> {
+    pub fn stderr() -> Self {
+        Self::new(io::stderr())
+    }
+}
+
+impl<W: Write> PrettyTracer<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Tracer for PrettyTracer<W> {
+    fn trace(&mut self, event: &TraceEvent) -> Result<(), String> {
+        let line = match event {
+            TraceEvent::Instruction {
+                ip,
+                opcode,
+                modes,
+                operands,
+            } => {
+                let modes = modes.iter().map(|m| mode_letter(*m)).collect::<String>();
+                let operands = operands
+                    .iter()
+                    .map(|o| o.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                format!("{:04}: {:?}({}) {}", ip, opcode, modes, operands)
+            }
+            TraceEvent::Write { address, value } => format!("      [{}] <- {}", address, value),
+            TraceEvent::RelativeBase { from, to } => format!("      rb {} -> {}", from, to),
+        };
+
+        writeln!(self.writer, "{}", line.trim_end())
+            .map_err(|e| format!("Failed to write trace: {}", e))
+    }
+}
+
+fn mode_letter(mode: ParameterMode) -> char {
+    match mode {
+        ParameterMode::Position => 'P',
+        ParameterMode::Immediate => 'I',
+        ParameterMode::Relative => 'R',
+    }
+}
+
+// One JSON object per line, for feeding into other tools
+pub struct JsonTracer<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonTracer<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Tracer for JsonTracer<W> {
+    fn trace(&mut self, event: &TraceEvent) -> Result<(), String> {
+        let line = match event {
+            TraceEvent::Instruction {
+                ip,
+                opcode,
+                modes,
+                operands,
+            } => format!(
+                "{{\"event\":\"instruction\",\"ip\":{},\"opcode\":\"{:?}\",\"modes\":[{}],\"operands\":[{}]}}",
+                ip,
+                opcode,
+                modes
+                    .iter()
+                    .map(|m| format!("\"{:?}\"", m))
+                    .collect::<Vec<String>>()
+                    .join(","),
+                operands
+                    .iter()
+                    .map(|o| o.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+            TraceEvent::Write { address, value } => format!(
+                "{{\"event\":\"write\",\"address\":{},\"value\":{}}}",
+                address, value
+            ),
+            TraceEvent::RelativeBase { from, to } => format!(
+                "{{\"event\":\"relative_base\",\"from\":{},\"to\":{}}}",
+                from, to
+            ),
+        };
+
+        writeln!(self.writer, "{}", line).map_err(|e| format!("Failed to write trace: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events() -> Vec<TraceEvent> {
+        vec![
+            TraceEvent::Instruction {
+                ip: 4,
+                opcode: Opcode::Add,
+                modes: vec![
+                    ParameterMode::Position,
+                    ParameterMode::Immediate,
+                    ParameterMode::Relative,
+                ],
+                operands: vec![3, -5, 10],
+            },
+            TraceEvent::Write {
+                address: 10,
+                value: -2,
+            },
+            TraceEvent::RelativeBase { from: 0, to: 7 },
+            TraceEvent::Instruction {
+                ip: 8,
+                opcode: Opcode::Halt,
+                modes: vec![],
+                operands: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn pretty_tracer() {
+        let mut tracer = PrettyTracer::new(Vec::new());
+        for event in events() {
+            tracer.trace(&event).unwrap();
+        }
+
+        let expected = "\
+0004: Add(PIR) 3, -5, 10
+      [10] <- -2
+      rb 0 -> 7
+0008: Halt()
+";
+
+        assert_eq!(expected, String::from_utf8(tracer.into_inner()).unwrap());
+    }
+
+    #[test]
+    fn json_tracer() {
+        let mut tracer = JsonTracer::new(Vec::new());
+        for event in events() {
+            tracer.trace(&event).unwrap();
+        }
+
+        let expected = "\
+{\"event\":\"instruction\",\"ip\":4,\"opcode\":\"Add\",\"modes\":[\"Position\",\"Immediate\",\"Relative\"],\"operands\":[3,-5,10]}
+{\"event\":\"write\",\"address\":10,\"value\":-2}
+{\"event\":\"relative_base\",\"from\":0,\"to\":7}
+{\"event\":\"instruction\",\"ip\":8,\"opcode\":\"Halt\",\"modes\":[],\"operands\":[]}
+";
+
+        assert_eq!(expected, String::from_utf8(tracer.into_inner()).unwrap());
+    }
+}